@@ -0,0 +1,30 @@
+//!Exposes exact version and features of `xxhash-rust`, as required by `Cargo.toml`, via `XXHASH_RUST_VERSION` and `XXHASH_RUST_FEATURES`
+//!
+//!`Cargo.lock` is not part of repository, so version is pinned in manifest instead.
+
+use std::path::Path;
+
+///Returns value of `key` within `[dependencies.<name>]` section of manifest.
+fn find_value<'a>(manifest: &'a str, name: &str, key: &str) -> Option<&'a str> {
+    let section = format!("[dependencies.{}]", name);
+    let mut lines = manifest.lines().skip_while(|line| line.trim() != section).skip(1);
+    lines.find_map(|line| match line.trim_start().starts_with('[') {
+        true => Some(None),
+        false => line.split_once('=').filter(|(line_key, _)| line_key.trim() == key).map(|(_, value)| Some(value.trim())),
+    }).flatten()
+}
+
+fn main() {
+    let path = Path::new(&std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+    println!("cargo:rerun-if-changed={}", path.display());
+    let manifest = std::fs::read_to_string(&path).expect("to read Cargo.toml");
+
+    //Hashes may differ across versions, so only exact requirement tells which one is compiled in
+    let version = find_value(&manifest, "xxhash-rust", "version").and_then(|version| version.trim_matches('"').strip_prefix('='));
+    let version = version.expect("xxhash-rust must be pinned to exact version in Cargo.toml");
+    let features = find_value(&manifest, "xxhash-rust", "features").unwrap_or("[]");
+    let features = features.trim_matches(['[', ']']).split(',').map(|feature| feature.trim().trim_matches('"')).filter(|feature| !feature.is_empty()).collect::<Vec<_>>();
+
+    println!("cargo:rustc-env=XXHASH_RUST_VERSION={}", version);
+    println!("cargo:rustc-env=XXHASH_RUST_FEATURES={}", features.join(", "));
+}
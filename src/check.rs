@@ -0,0 +1,213 @@
+//!Parsing of checksum files.
+
+use crate::HashResult;
+use crate::output::{Encoding, HashFormat};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+///Format of checksum lines.
+pub enum InputFormat {
+    ///Detects format of every line
+    Auto,
+    ///`<hash>  <file>`
+    Coreutils,
+    ///`<LABEL> (<file>) = <hash>`
+    Bsd,
+    ///`<file>:<hash>`
+    Colon,
+}
+
+impl core::str::FromStr for InputFormat {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.eq_ignore_ascii_case("auto") {
+            Ok(Self::Auto)
+        } else if text.eq_ignore_ascii_case("coreutils") {
+            Ok(Self::Coreutils)
+        } else if text.eq_ignore_ascii_case("bsd") {
+            Ok(Self::Bsd)
+        } else if text.eq_ignore_ascii_case("colon") {
+            Ok(Self::Colon)
+        } else {
+            Err(())
+        }
+    }
+}
+
+///Parsed checksum line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CheckLine<'a> {
+    ///Algorithm label of bsd line.
+    pub label: Option<&'a str>,
+    ///Expected hash.
+    pub hash: &'a str,
+    ///File to verify.
+    pub file: &'a str,
+}
+
+///Parses checksum line in format `<hash>  <file>`
+fn parse_coreutils_line(line: &str) -> Option<CheckLine<'_>> {
+    let (hash, file) = match line.split_once("  ") {
+        Some(result) => result,
+        //Binary mode marker of coreutils
+        None => line.split_once(" *")?,
+    };
+
+    if hash.is_empty() || file.is_empty() {
+        None
+    } else {
+        Some(CheckLine {
+            label: None,
+            hash,
+            file,
+        })
+    }
+}
+
+///Parses checksum line in format `<LABEL> (<file>) = <hash>`
+fn parse_bsd_line(line: &str) -> Option<CheckLine<'_>> {
+    let (label, rest) = line.split_once(" (")?;
+    let (file, hash) = rest.rsplit_once(") = ")?;
+
+    if label.is_empty() || label.contains(' ') || hash.is_empty() || hash.contains(' ') || file.is_empty() {
+        None
+    } else {
+        Some(CheckLine {
+            label: Some(label),
+            hash,
+            file,
+        })
+    }
+}
+
+///Parses checksum line in format `<file>:<hash>`, allowing colons in file name.
+fn parse_colon_line(line: &str) -> Option<CheckLine<'_>> {
+    let (file, hash) = line.rsplit_once(':')?;
+
+    if hash.is_empty() || hash.contains(char::is_whitespace) || file.is_empty() {
+        None
+    } else {
+        Some(CheckLine {
+            label: None,
+            hash,
+            file,
+        })
+    }
+}
+
+///Parses checksum line.
+pub fn parse_line(line: &str, format: InputFormat) -> Option<CheckLine<'_>> {
+    match format {
+        //Most distinctive format goes first
+        InputFormat::Auto => parse_bsd_line(line).or_else(|| parse_coreutils_line(line)).or_else(|| parse_colon_line(line)),
+        InputFormat::Coreutils => parse_coreutils_line(line),
+        InputFormat::Bsd => parse_bsd_line(line),
+        InputFormat::Colon => parse_colon_line(line),
+    }
+}
+
+///Checks whether `expected` matches `hash`, either as formatted by `format` or as hex or decimal value.
+///
+///Comparison ignores surrounding whitespace, as well as case of anything but base64.
+pub fn is_expected(format: &HashFormat, hash: HashResult, expected: &str) -> bool {
+    let expected = expected.trim();
+    let formatted = format.format(hash);
+    let is_match = match format.encoding {
+        Encoding::Base64 | Encoding::Base64Url => formatted == expected,
+        _ => formatted.eq_ignore_ascii_case(expected),
+    };
+    if is_match {
+        return true;
+    }
+
+    let hex = expected.strip_prefix("0x").or_else(|| expected.strip_prefix("0X")).unwrap_or(expected);
+    u128::from_str_radix(hex, 16).ok() == Some(hash.as_u128()) || expected.parse::<u128>().ok() == Some(hash.as_u128())
+}
+
+///Returns whether `file` is checksum file stored next to hashed file.
+pub fn is_sidecar(file: &str) -> bool {
+    file.ends_with(".xxh") || file.ends_with(".xxh3")
+}
+
+#[derive(Default)]
+///Outcome of verifying checksums.
+pub struct Summary {
+    ///Number of matched checksums.
+    pub ok: usize,
+    ///Number of mismatched checksums and files that could not be read.
+    pub failed: usize,
+    ///Number of files that do not exist.
+    pub missing: usize,
+    ///Number of improperly formatted lines.
+    pub malformed: usize,
+    ///Number of checksum files that could not be read or verified no file.
+    pub errors: usize,
+}
+
+impl Summary {
+    ///Returns whether verification succeeded, treating improperly formatted lines as failure with `is_strict`.
+    #[inline(always)]
+    pub fn is_ok(&self, is_strict: bool) -> bool {
+        self.failed == 0 && self.missing == 0 && self.errors == 0 && (!is_strict || self.malformed == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line<'a>(label: Option<&'a str>, hash: &'a str, file: &'a str) -> Option<CheckLine<'a>> {
+        Some(CheckLine {
+            label,
+            hash,
+            file,
+        })
+    }
+
+    #[test]
+    fn should_parse_line_of_each_format() {
+        assert_eq!(parse_line("abc  dir/a b", InputFormat::Coreutils), line(None, "abc", "dir/a b"));
+        assert_eq!(parse_line("abc *dir/a", InputFormat::Coreutils), line(None, "abc", "dir/a"));
+        assert_eq!(parse_line("XXH64 (dir/(a) = b) = abc", InputFormat::Bsd), line(Some("XXH64"), "abc", "dir/(a) = b"));
+        assert_eq!(parse_line("dir/a:b:abc", InputFormat::Colon), line(None, "abc", "dir/a:b"));
+
+        assert_eq!(parse_line("abc dir/a", InputFormat::Coreutils), None);
+        assert_eq!(parse_line("XXH 64 (a) = abc", InputFormat::Bsd), None);
+        assert_eq!(parse_line("XXH64 (a) = ab c", InputFormat::Bsd), None);
+        assert_eq!(parse_line("dir/a:", InputFormat::Colon), None);
+        assert_eq!(parse_line("abc  a", InputFormat::Colon), None);
+    }
+
+    #[test]
+    fn should_parse_windows_path() {
+        let file = "C:\\data\\file.bin";
+        assert_eq!(parse_line("abc  C:\\data\\file.bin", InputFormat::Auto), line(None, "abc", file));
+        assert_eq!(parse_line("XXH64 (C:\\data\\file.bin) = abc", InputFormat::Auto), line(Some("XXH64"), "abc", file));
+        //Drive letter is not taken for separator
+        assert_eq!(parse_line("C:\\data\\file.bin:abc", InputFormat::Auto), line(None, "abc", file));
+    }
+
+    #[test]
+    fn should_detect_format_of_each_line() {
+        assert_eq!(parse_line("abc  a", InputFormat::Auto), line(None, "abc", "a"));
+        assert_eq!(parse_line("XXH64 (a) = abc", InputFormat::Auto), line(Some("XXH64"), "abc", "a"));
+        assert_eq!(parse_line("XXH3 (a  b) = abc", InputFormat::Auto), line(Some("XXH3"), "abc", "a  b"));
+        assert_eq!(parse_line("a:abc", InputFormat::Auto), line(None, "abc", "a"));
+        assert_eq!(parse_line("abc", InputFormat::Auto), None);
+    }
+
+    #[test]
+    fn should_accept_expected_hash_in_any_representation() {
+        let format = HashFormat {
+            encoding: Encoding::Base64,
+            uppercase: false,
+            width: None,
+        };
+        let hash = HashResult::U32(0x02cc5d05);
+        for expected in ["AsxdBQ==", "02cc5d05", "02CC5D05", "0x2cc5d05", "46947589", " 02cc5d05 "] {
+            assert!(is_expected(&format, hash, expected), "{}", expected);
+        }
+        assert!(!is_expected(&format, hash, "02cc5d06"));
+        assert!(!is_expected(&format, hash, "asxdbq=="));
+    }
+}
@@ -0,0 +1,174 @@
+//!Defaults read from configuration file.
+//!
+//!File is looked up at `$XDG_CONFIG_HOME/xxhash-cli/config.toml`, falling back to
+//!`$HOME/.config/xxhash-cli/config.toml`, or at `%APPDATA%\xxhash-cli\config.toml` on Windows.
+//!Missing file is the same as empty one.
+//!
+//!File consists of `key = value` lines, with `#` comments and values being
+//!either quoted strings or bare words and numbers:
+//!
+//!```toml
+//!algorithm = "xxh64"
+//!format = "coreutils"
+//!buffer_size = 65536
+//!color = "never"
+//!separator = "\t"
+//!```
+//!
+//!Configured values are only defaults: command line options take priority over environment variables, which take priority over file.
+//!Value, that conflicts with options of command line, is ignored.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use xxhash_cli::HashKind;
+
+use crate::{Setting, unknown_kind};
+
+///Defaults specified by configuration file.
+#[derive(Default)]
+pub struct Config {
+    ///Algorithm to use when none is specified.
+    pub algorithm: Option<HashKind>,
+    ///Defaults of options, in order of file.
+    pub settings: Vec<Setting>,
+}
+
+fn config_path() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => PathBuf::from(std::env::var_os("APPDATA")?),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("xxhash-cli").join("config.toml"))
+}
+
+///Parses quoted string with basic escapes or bare value.
+fn parse_value(value: &str) -> Option<String> {
+    let value = match value.strip_prefix('"') {
+        Some(value) => value.strip_suffix('"')?,
+        None => return match value.contains(['"', ' ']) {
+            true => None,
+            false => Some(value.to_owned()),
+        },
+    };
+
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => result.push(match chars.next()? {
+                't' => '\t',
+                'n' => '\n',
+                '"' => '"',
+                '\\' => '\\',
+                _ => return None,
+            }),
+            '"' => return None,
+            ch => result.push(ch),
+        }
+    }
+    Some(result)
+}
+
+///Parses content of configuration file.
+fn parse(text: &str) -> Result<Config, String> {
+    let mut config = Config::default();
+
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let error = |message: String| format!("{}: {}", idx + 1, message);
+        let (key, value) = line.split_once('=').ok_or_else(|| error("expected key = value".to_owned()))?;
+        let key = key.trim();
+        let value = parse_value(value.trim()).ok_or_else(|| error(format!("invalid value of {}", key)))?;
+
+        let setting = match key {
+            "algorithm" => {
+                config.algorithm = Some(value.parse().map_err(|_| error(unknown_kind(&value)))?);
+                continue;
+            },
+            "format" => match value.parse() {
+                Ok(format) => Setting::Format(format),
+                Err(_) => return Err(error(format!("unknown format '{}'", value))),
+            },
+            "color" => match value.parse() {
+                //NO_COLOR takes priority over file
+                Ok(_) if std::env::var_os("NO_COLOR").map_or(false, |value| !value.is_empty()) => continue,
+                Ok(color) => Setting::Color(color),
+                Err(_) => return Err(error(format!("unknown color '{}'", value))),
+            },
+            "buffer_size" => match value.parse::<usize>() {
+                Ok(size) if size > 0 => Setting::BufferSize(size),
+                _ => return Err(error(format!("invalid buffer_size '{}'", value))),
+            },
+            "separator" => Setting::Separator(value),
+            _ => return Err(error(format!("unknown key '{}'", key))),
+        };
+        config.settings.push(setting);
+    }
+
+    Ok(config)
+}
+
+///Loads configuration file, if it exists.
+pub fn load() -> Result<Config, String> {
+    let path = match config_path() {
+        Some(path) => path,
+        None => return Ok(Config::default()),
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(text) => parse(&text).map_err(|error| format!("{}:{}", path.display(), error)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(error) => Err(format!("{}: cannot read: {}", path.display(), error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_quoted_and_bare_values() {
+        assert_eq!(parse_value("xxh64").as_deref(), Some("xxh64"));
+        assert_eq!(parse_value("65536").as_deref(), Some("65536"));
+        assert_eq!(parse_value("\"a b\"").as_deref(), Some("a b"));
+        assert_eq!(parse_value("\"\\t\\n\\\"\\\\\"").as_deref(), Some("\t\n\"\\"));
+        assert_eq!(parse_value("\"\"").as_deref(), Some(""));
+
+        assert_eq!(parse_value("a b"), None);
+        assert_eq!(parse_value("a\"b"), None);
+        assert_eq!(parse_value("\"ab"), None);
+        assert_eq!(parse_value("\"a\"b\""), None);
+        assert_eq!(parse_value("\"\\x\""), None);
+        assert_eq!(parse_value("\"\\\""), None);
+    }
+
+    #[test]
+    fn should_parse_settings_in_order_of_file() {
+        let config = parse("# comment\n\nalgorithm = \"xxh64\"\nformat = coreutils\n  buffer_size=65536  \nseparator = \"\\t\"\n").expect("to parse");
+        assert_eq!(config.algorithm, Some(HashKind::Xxh64));
+        assert!(matches!(config.settings.as_slice(), [Setting::Format(xxhash_cli::output::Format::Coreutils), Setting::BufferSize(65536), Setting::Separator(separator)] if separator == "\t"));
+
+        let config = parse("").expect("to parse");
+        assert_eq!(config.algorithm, None);
+        assert!(config.settings.is_empty());
+    }
+
+    #[test]
+    fn should_report_line_of_invalid_setting() {
+        assert_eq!(parse("\nformat").err().as_deref(), Some("2: expected key = value"));
+        assert_eq!(parse("jobs = 2").err().as_deref(), Some("1: unknown key 'jobs'"));
+        assert_eq!(parse("separator = a b").err().as_deref(), Some("1: invalid value of separator"));
+        assert_eq!(parse("format = gnu").err().as_deref(), Some("1: unknown format 'gnu'"));
+        assert_eq!(parse("color = red").err().as_deref(), Some("1: unknown color 'red'"));
+        assert_eq!(parse("buffer_size = 0").err().as_deref(), Some("1: invalid buffer_size '0'"));
+        assert_eq!(parse("buffer_size = -1").err().as_deref(), Some("1: invalid buffer_size '-1'"));
+        assert!(parse("algorithm = md5").err().expect("error").starts_with("1: unknown algorithm 'md5'"));
+    }
+}
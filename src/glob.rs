@@ -0,0 +1,153 @@
+//!Wildcard expansion of file arguments.
+//!
+//!Supports `*`, `?` and character classes `[abc]`, `[a-z]`, `[!abc]` within single path component.
+//!Like in shells, wildcards do not match leading `.` unless pattern starts with it.
+
+use std::fs;
+use std::path::MAIN_SEPARATOR;
+
+fn is_separator(ch: char) -> bool {
+    ch == '/' || ch == MAIN_SEPARATOR
+}
+
+///Returns whether `text` contains wildcards.
+pub fn is_pattern(text: &str) -> bool {
+    text.contains(['*', '?', '['])
+}
+
+///Matches character class at the start of `class`, returning result and length of class.
+///
+///Returns `None` if class is not terminated, in which case `[` is treated literally.
+fn match_class(class: &[char], ch: char) -> Option<(bool, usize)> {
+    let mut idx = 1;
+    let is_negated = matches!(class.get(idx), Some('!') | Some('^'));
+    if is_negated {
+        idx += 1;
+    }
+
+    let start = idx;
+    let mut is_match = false;
+    while let Some(&first) = class.get(idx) {
+        if first == ']' && idx > start {
+            return Some((is_match != is_negated, idx + 1));
+        }
+
+        match (class.get(idx + 1), class.get(idx + 2)) {
+            (Some('-'), Some(&last)) if last != ']' => {
+                is_match |= first <= ch && ch <= last;
+                idx += 3;
+            },
+            _ => {
+                is_match |= first == ch;
+                idx += 1;
+            }
+        }
+    }
+
+    None
+}
+
+///Returns whether `name` matches wildcard `pattern` in whole.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let mut pattern_idx = 0;
+    let mut name_idx = 0;
+    //Position of last star and name position it is matched up to, for backtracking.
+    let mut star = None;
+
+    while name_idx < name.len() {
+        let step = match pattern.get(pattern_idx) {
+            Some('*') => {
+                star = Some((pattern_idx, name_idx));
+                pattern_idx += 1;
+                continue;
+            },
+            Some('?') => Some(1),
+            Some('[') => match match_class(&pattern[pattern_idx..], name[name_idx]) {
+                Some((true, len)) => Some(len),
+                Some((false, _)) => None,
+                None => (name[name_idx] == '[').then_some(1),
+            },
+            Some(&ch) => (ch == name[name_idx]).then_some(1),
+            None => None,
+        };
+
+        match (step, star) {
+            (Some(len), _) => {
+                pattern_idx += len;
+                name_idx += 1;
+            },
+            (None, Some((star_idx, star_name_idx))) => {
+                pattern_idx = star_idx + 1;
+                name_idx = star_name_idx + 1;
+                star = Some((star_idx, name_idx));
+            },
+            (None, None) => return false,
+        }
+    }
+
+    pattern[pattern_idx..].iter().all(|ch| *ch == '*')
+}
+
+///Returns whether `path` matches `pattern` component by component, so that wildcards do not match separator.
+pub fn matches_path(pattern: &str, path: &str) -> bool {
+    let mut patterns = pattern.split(is_separator);
+    let mut names = path.split(is_separator);
+
+    loop {
+        match (patterns.next(), names.next()) {
+            (Some(pattern), Some(name)) => if !matches(pattern, name) {
+                return false;
+            },
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+fn join(base: &str, name: &str) -> String {
+    match base.is_empty() || base.ends_with(is_separator) {
+        true => format!("{}{}", base, name),
+        false => format!("{}{}{}", base, MAIN_SEPARATOR, name),
+    }
+}
+
+///Expands `pattern` into sorted list of existing paths matching it.
+pub fn expand(pattern: &str) -> Vec<String> {
+    let mut paths = vec![String::new()];
+
+    for part in pattern.split(is_separator) {
+        if part.is_empty() {
+            //Leading separator of absolute path or repeated separator
+            for path in paths.iter_mut() {
+                path.push(MAIN_SEPARATOR);
+            }
+        } else if !is_pattern(part) {
+            for path in paths.iter_mut() {
+                *path = join(path, part);
+            }
+        } else {
+            let mut matched = Vec::new();
+            for path in paths.iter() {
+                let entries = match fs::read_dir(if path.is_empty() { "." } else { path.as_str() }) {
+                    Ok(entries) => entries,
+                    Err(_) => continue,
+                };
+
+                let mut names: Vec<String> = entries.filter_map(|entry| entry.ok())
+                                                    .filter_map(|entry| entry.file_name().into_string().ok())
+                                                    .filter(|name| !name.starts_with('.') || part.starts_with('.'))
+                                                    .filter(|name| matches(part, name))
+                                                    .collect();
+                names.sort();
+                matched.extend(names.iter().map(|name| join(path, name)));
+            }
+            paths = matched;
+        }
+    }
+
+    paths.retain(|path| fs::symlink_metadata(path).is_ok());
+    paths
+}
@@ -0,0 +1,601 @@
+//!Streaming gzip decoder.
+//!
+//!Implements inflate as described by RFC 1951 within gzip members of RFC 1952.
+//!Concatenated members are decoded as single stream, like `gzip -d` does.
+
+use std::io::{self, Read, BufRead, BufReader};
+
+const MAX_BITS: usize = 15;
+///Size of history, that back references may refer to.
+const WINDOW_SIZE: usize = 32 * 1024;
+///Number of decoded bytes, after which decoding is paused to hand them out.
+const OUTPUT_SIZE: usize = 32 * 1024;
+
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+///Order in which code length code lengths are stored.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+const FLAG_HCRC: u8 = 2;
+const FLAG_EXTRA: u8 = 4;
+const FLAG_NAME: u8 = 8;
+const FLAG_COMMENT: u8 = 16;
+
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+///Computes CRC-32 as used by gzip.
+struct Crc32 {
+    table: [u32; 256],
+    value: u32,
+}
+
+impl Crc32 {
+    fn new() -> Self {
+        let mut table = [0u32; 256];
+        for (idx, entry) in table.iter_mut().enumerate() {
+            let mut value = idx as u32;
+            for _ in 0..8 {
+                value = match value & 1 {
+                    1 => 0xEDB88320 ^ (value >> 1),
+                    _ => value >> 1,
+                };
+            }
+            *entry = value;
+        }
+
+        Self {
+            table,
+            value: 0,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        let mut value = !self.value;
+        for byte in data {
+            value = self.table[((value ^ *byte as u32) & 0xFF) as usize] ^ (value >> 8);
+        }
+        self.value = !value;
+    }
+}
+
+///Reads input bit by bit, least significant first.
+struct BitReader<R> {
+    inner: BufReader<R>,
+    bits: u32,
+    count: u32,
+}
+
+impl<R: Read> BitReader<R> {
+    fn byte(&mut self) -> io::Result<u8> {
+        let byte = match self.inner.fill_buf()?.first() {
+            Some(byte) => *byte,
+            None => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "gzip stream is truncated")),
+        };
+        self.inner.consume(1);
+        Ok(byte)
+    }
+
+    fn bits(&mut self, count: u32) -> io::Result<u32> {
+        while self.count < count {
+            self.bits |= (self.byte()? as u32) << self.count;
+            self.count += 8;
+        }
+
+        let result = self.bits & ((1u32 << count) - 1);
+        self.bits >>= count;
+        self.count -= count;
+        Ok(result)
+    }
+
+    ///Discards bits up to the byte boundary.
+    fn align(&mut self) {
+        self.bits = 0;
+        self.count = 0;
+    }
+
+    fn u16_le(&mut self) -> io::Result<u16> {
+        Ok(self.bits(16)? as u16)
+    }
+
+    fn u32_le(&mut self) -> io::Result<u32> {
+        let low = self.bits(16)?;
+        Ok(low | (self.bits(16)? << 16))
+    }
+
+    fn is_eof(&mut self) -> io::Result<bool> {
+        Ok(self.count == 0 && self.inner.fill_buf()?.is_empty())
+    }
+}
+
+///Canonical Huffman code.
+struct Huffman {
+    ///Number of codes of each length.
+    counts: [u16; MAX_BITS + 1],
+    ///Symbols ordered by their codes.
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> io::Result<Self> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        //Reject over-subscribed codes, incomplete are allowed for single distance code
+        let mut left = 1i32;
+        for count in counts.iter().skip(1) {
+            left = (left << 1) - *count as i32;
+            if left < 0 {
+                return Err(invalid("gzip stream has invalid huffman code"));
+            }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for length in 1..=MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+
+        let mut symbols = vec![0u16; offsets[MAX_BITS + 1] as usize];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                let offset = &mut offsets[*length as usize];
+                symbols[*offset as usize] = symbol as u16;
+                *offset += 1;
+            }
+        }
+
+        Ok(Self {
+            counts,
+            symbols,
+        })
+    }
+
+    fn fixed() -> (Self, Self) {
+        let mut lengths = [0u8; 288];
+        lengths[..144].fill(8);
+        lengths[144..256].fill(9);
+        lengths[256..280].fill(7);
+        lengths[280..].fill(8);
+
+        //Fixed codes are complete, so construction cannot fail
+        let literals = Self::new(&lengths).expect("valid fixed literal code");
+        let distances = Self::new(&[5u8; 30]).expect("valid fixed distance code");
+        (literals, distances)
+    }
+
+    fn decode<R: Read>(&self, input: &mut BitReader<R>) -> io::Result<u16> {
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+
+        for count in self.counts.iter().skip(1) {
+            let count = *count as i32;
+            code |= input.bits(1)? as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(invalid("gzip stream has invalid huffman code"))
+    }
+}
+
+enum State {
+    ///Expects gzip member header.
+    Header,
+    ///Expects deflate block header.
+    Block,
+    ///Within stored block with number of bytes left.
+    Stored(usize),
+    ///Within compressed block.
+    Compressed {
+        literals: Huffman,
+        distances: Huffman,
+    },
+    ///Expects gzip member trailer.
+    Trailer,
+    Done,
+}
+
+///Decoder of gzip stream, yielding decompressed data.
+pub struct Decoder<R> {
+    input: BitReader<R>,
+    state: State,
+    ///Whether current block is final block of the member.
+    is_final: bool,
+    ///Recently decoded data, that back references may refer to.
+    history: Vec<u8>,
+    ///Decoded data not yet read.
+    output: Vec<u8>,
+    output_pos: usize,
+    crc: Crc32,
+    size: u32,
+}
+
+impl<R: Read> Decoder<R> {
+    ///Creates new decoder reading compressed stream from `inner`.
+    pub fn new(inner: R) -> Self {
+        Self {
+            input: BitReader {
+                inner: BufReader::new(inner),
+                bits: 0,
+                count: 0,
+            },
+            state: State::Header,
+            is_final: false,
+            history: Vec::with_capacity(2 * WINDOW_SIZE),
+            output: Vec::with_capacity(OUTPUT_SIZE + 258),
+            output_pos: 0,
+            crc: Crc32::new(),
+            size: 0,
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.output.push(byte);
+        self.history.push(byte);
+    }
+
+    fn read_header(&mut self) -> io::Result<()> {
+        let input = &mut self.input;
+        if input.byte()? != 0x1f || input.byte()? != 0x8b {
+            return Err(invalid("not in gzip format"));
+        }
+        if input.byte()? != 8 {
+            return Err(invalid("gzip stream uses unknown compression method"));
+        }
+
+        let flags = input.byte()?;
+        //Modification time, extra flags and OS
+        for _ in 0..6 {
+            input.byte()?;
+        }
+
+        if flags & FLAG_EXTRA != 0 {
+            let len = input.u16_le()?;
+            for _ in 0..len {
+                input.byte()?;
+            }
+        }
+        for flag in [FLAG_NAME, FLAG_COMMENT] {
+            if flags & flag != 0 {
+                while input.byte()? != 0 {}
+            }
+        }
+        if flags & FLAG_HCRC != 0 {
+            input.u16_le()?;
+        }
+
+        self.crc = Crc32::new();
+        self.size = 0;
+        self.state = State::Block;
+        Ok(())
+    }
+
+    fn read_block_header(&mut self) -> io::Result<()> {
+        self.is_final = self.input.bits(1)? == 1;
+        self.state = match self.input.bits(2)? {
+            0 => {
+                self.input.align();
+                let len = self.input.u16_le()?;
+                let nlen = self.input.u16_le()?;
+                if len != !nlen {
+                    return Err(invalid("gzip stream has corrupted stored block"));
+                }
+                State::Stored(len as usize)
+            },
+            1 => {
+                let (literals, distances) = Huffman::fixed();
+                State::Compressed {
+                    literals,
+                    distances,
+                }
+            },
+            2 => self.read_dynamic_codes()?,
+            _ => return Err(invalid("gzip stream has invalid block type")),
+        };
+        Ok(())
+    }
+
+    fn read_dynamic_codes(&mut self) -> io::Result<State> {
+        let input = &mut self.input;
+        let literals_len = input.bits(5)? as usize + 257;
+        let distances_len = input.bits(5)? as usize + 1;
+        let code_lengths_len = input.bits(4)? as usize + 4;
+        if literals_len > 286 || distances_len > 30 {
+            return Err(invalid("gzip stream has too many codes"));
+        }
+
+        let mut code_lengths = [0u8; 19];
+        for idx in CODE_LENGTH_ORDER.iter().take(code_lengths_len) {
+            code_lengths[*idx] = input.bits(3)? as u8;
+        }
+        let code_lengths = Huffman::new(&code_lengths)?;
+
+        let mut lengths = vec![0u8; literals_len + distances_len];
+        let mut idx = 0;
+        while idx < lengths.len() {
+            let (length, repeat) = match code_lengths.decode(input)? {
+                symbol @ 0..=15 => (symbol as u8, 1),
+                16 => match idx.checked_sub(1) {
+                    Some(prev) => (lengths[prev], 3 + input.bits(2)? as usize),
+                    None => return Err(invalid("gzip stream repeats missing code length")),
+                },
+                17 => (0, 3 + input.bits(3)? as usize),
+                _ => (0, 11 + input.bits(7)? as usize),
+            };
+
+            if idx + repeat > lengths.len() {
+                return Err(invalid("gzip stream has too many code lengths"));
+            }
+            lengths[idx..idx + repeat].fill(length);
+            idx += repeat;
+        }
+
+        if lengths[256] == 0 {
+            return Err(invalid("gzip stream has no end of block code"));
+        }
+
+        Ok(State::Compressed {
+            literals: Huffman::new(&lengths[..literals_len])?,
+            distances: Huffman::new(&lengths[literals_len..])?,
+        })
+    }
+
+    fn read_trailer(&mut self) -> io::Result<()> {
+        self.input.align();
+        let crc = self.input.u32_le()?;
+        let size = self.input.u32_le()?;
+        if crc != self.crc.value {
+            return Err(invalid("gzip stream has invalid checksum"));
+        } else if size != self.size {
+            return Err(invalid("gzip stream has invalid length"));
+        }
+
+        self.state = match self.input.is_eof()? {
+            true => State::Done,
+            false => State::Header,
+        };
+        Ok(())
+    }
+
+    ///Decodes next portion of data into output.
+    fn step(&mut self) -> io::Result<()> {
+        match core::mem::replace(&mut self.state, State::Done) {
+            State::Header => self.read_header(),
+            State::Block => self.read_block_header(),
+            State::Trailer => self.read_trailer(),
+            State::Done => Ok(()),
+            State::Stored(mut remaining) => {
+                while remaining > 0 && self.output.len() < OUTPUT_SIZE {
+                    let byte = self.input.byte()?;
+                    self.push(byte);
+                    remaining -= 1;
+                }
+
+                self.state = match remaining {
+                    0 => self.block_end(),
+                    remaining => State::Stored(remaining),
+                };
+                Ok(())
+            },
+            State::Compressed { literals, distances } => {
+                while self.output.len() < OUTPUT_SIZE {
+                    let symbol = literals.decode(&mut self.input)? as usize;
+                    if symbol < 256 {
+                        self.push(symbol as u8);
+                        continue;
+                    } else if symbol == 256 {
+                        self.state = self.block_end();
+                        return Ok(());
+                    }
+
+                    let symbol = symbol - 257;
+                    if symbol >= LENGTH_BASE.len() {
+                        return Err(invalid("gzip stream has invalid length code"));
+                    }
+                    let length = LENGTH_BASE[symbol] as usize + self.input.bits(LENGTH_EXTRA[symbol] as u32)? as usize;
+
+                    let symbol = distances.decode(&mut self.input)? as usize;
+                    if symbol >= DIST_BASE.len() {
+                        return Err(invalid("gzip stream has invalid distance code"));
+                    }
+                    let distance = DIST_BASE[symbol] as usize + self.input.bits(DIST_EXTRA[symbol] as u32)? as usize;
+                    if distance > self.history.len() {
+                        return Err(invalid("gzip stream refers to data before its start"));
+                    }
+
+                    let start = self.history.len() - distance;
+                    for idx in start..start + length {
+                        let byte = self.history[idx];
+                        self.push(byte);
+                    }
+                }
+
+                self.state = State::Compressed {
+                    literals,
+                    distances,
+                };
+                Ok(())
+            }
+        }
+    }
+
+    fn block_end(&self) -> State {
+        match self.is_final {
+            true => State::Trailer,
+            false => State::Block,
+        }
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.output_pos == self.output.len() {
+            if let State::Done = self.state {
+                return Ok(0);
+            }
+
+            self.output.clear();
+            self.output_pos = 0;
+            //Back references only reach window size
+            if self.history.len() > 2 * WINDOW_SIZE {
+                self.history.drain(..self.history.len() - WINDOW_SIZE);
+            }
+
+            self.step()?;
+            self.crc.update(&self.output);
+            self.size = self.size.wrapping_add(self.output.len() as u32);
+        }
+
+        let size = buf.len().min(self.output.len() - self.output_pos);
+        buf[..size].copy_from_slice(&self.output[self.output_pos..self.output_pos + size]);
+        self.output_pos += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &[u8] = b"hello hello hello";
+    ///`TEXT` compressed by zlib into single fixed block.
+    const FIXED: [u8; 10] = [0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00];
+    ///`lines()` compressed by zlib into single dynamic block.
+    const DYNAMIC: [u8; 150] = [
+        0x95, 0xd4, 0xcb, 0x0d, 0x02, 0x41, 0x0c, 0x04, 0xd1, 0x3b, 0x51, 0x38, 0x00, 0x0e, 0xd8, 0xcd, 0x37, 0x9c, 0x11, 0x32, 0xcc, 0x4a, 0xc3, 0x82, 0x18, 0x4b, 0x10, 0x3e, 0x22, 0x84,
+        0xba, 0xd7, 0xed, 0xa9, 0x7b, 0x2c, 0x6b, 0xda, 0xce, 0x9e, 0x37, 0xab, 0xfc, 0xd6, 0xd6, 0xaa, 0xb7, 0xb2, 0x77, 0xbe, 0xb2, 0xd5, 0xb4, 0xcf, 0x52, 0xdd, 0xe6, 0xa3, 0x8d, 0x61,
+        0xd7, 0xde, 0xd6, 0x7b, 0xce, 0xcd, 0xf8, 0xf7, 0x0e, 0xfb, 0x80, 0xbd, 0x60, 0xbf, 0x87, 0xfd, 0x01, 0xf6, 0x47, 0xd8, 0x9f, 0x60, 0x7f, 0x86, 0xfd, 0x85, 0x7a, 0x61, 0x60, 0x2a,
+        0xec, 0x94, 0xd8, 0xa9, 0xb1, 0x53, 0x64, 0xa7, 0xca, 0x4e, 0x99, 0x9d, 0x3a, 0x3b, 0x85, 0x76, 0x2a, 0x1d, 0x54, 0x3a, 0xf0, 0x96, 0xa9, 0x74, 0x50, 0xe9, 0xa0, 0xd2, 0x41, 0xa5,
+        0x83, 0x4a, 0x07, 0x95, 0x0e, 0x2a, 0x1d, 0x54, 0x5a, 0x54, 0x5a, 0x54, 0x5a, 0xf8, 0xb6, 0xa9, 0xb4, 0xa8, 0xb4, 0xa8, 0xb4, 0xa8, 0xb4, 0xa8, 0xb4, 0xa8, 0xb4, 0x80, 0xf4, 0x0f,
+    ];
+
+    fn lines() -> Vec<u8> {
+        (0..40).flat_map(|idx| format!("line {} of text, that repeats with small changes\n", idx).into_bytes()).collect()
+    }
+
+    fn crc32(data: &[u8]) -> u32 {
+        let mut crc = Crc32::new();
+        crc.update(data);
+        crc.value
+    }
+
+    ///Wraps deflate stored block of `data`.
+    fn stored(data: &[u8], is_final: bool) -> Vec<u8> {
+        let len = data.len() as u16;
+        let mut block = vec![is_final as u8];
+        block.extend_from_slice(&len.to_le_bytes());
+        block.extend_from_slice(&(!len).to_le_bytes());
+        block.extend_from_slice(data);
+        block
+    }
+
+    ///Wraps `deflate` stream of `data` into gzip member with optional fields of `flags`.
+    fn member(flags: u8, deflate: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut member = vec![0x1f, 0x8b, 8, flags, 0, 0, 0, 0, 0, 255];
+        if flags & FLAG_EXTRA != 0 {
+            member.extend_from_slice(&[4, 0, b'x', b'x', 2, 0]);
+        }
+        if flags & FLAG_NAME != 0 {
+            member.extend_from_slice(b"name.txt\0");
+        }
+        if flags & FLAG_COMMENT != 0 {
+            member.extend_from_slice(b"comment\0");
+        }
+        if flags & FLAG_HCRC != 0 {
+            let crc = crc32(&member) as u16;
+            member.extend_from_slice(&crc.to_le_bytes());
+        }
+        member.extend_from_slice(deflate);
+        member.extend_from_slice(&crc32(data).to_le_bytes());
+        member.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        member
+    }
+
+    fn decode(data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut output = Vec::new();
+        Decoder::new(data).read_to_end(&mut output)?;
+        Ok(output)
+    }
+
+    fn decode_error(data: &[u8]) -> String {
+        decode(data).expect_err("to fail").to_string()
+    }
+
+    #[test]
+    fn should_compute_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn should_decode_each_block_type() {
+        assert_eq!(decode(&member(0, &stored(TEXT, true), TEXT)).expect("to decode"), TEXT);
+        assert_eq!(decode(&member(0, &stored(b"", true), b"")).expect("to decode"), b"");
+        assert_eq!(decode(&member(0, &FIXED, TEXT)).expect("to decode"), TEXT);
+        assert_eq!(decode(&member(0, &DYNAMIC, &lines())).expect("to decode"), lines());
+    }
+
+    #[test]
+    fn should_decode_multiple_blocks_and_members() {
+        //Stored blocks, that are larger than output buffer
+        let data = (0..100_000u32).map(|idx| (idx * 31 % 251) as u8).collect::<Vec<_>>();
+        let deflate = [stored(&data[..60_000], false), stored(&data[60_000..], true)].concat();
+        assert_eq!(decode(&member(0, &deflate, &data)).expect("to decode"), data);
+
+        //Fixed block goes from byte boundary, where stored block ends
+        let deflate = [stored(b"abc", false), FIXED.to_vec()].concat();
+        assert_eq!(decode(&member(0, &deflate, b"abchello hello hello")).expect("to decode"), b"abchello hello hello");
+
+        let stream = [member(0, &DYNAMIC, &lines()), member(0, &FIXED, TEXT), member(0, &stored(b"", true), b"")].concat();
+        assert_eq!(decode(&stream).expect("to decode"), [lines(), TEXT.to_vec()].concat());
+    }
+
+    #[test]
+    fn should_skip_optional_header_fields() {
+        for flags in [FLAG_EXTRA, FLAG_NAME, FLAG_COMMENT, FLAG_HCRC, FLAG_EXTRA | FLAG_NAME | FLAG_COMMENT | FLAG_HCRC] {
+            assert_eq!(decode(&member(flags, &FIXED, TEXT)).expect("to decode"), TEXT, "flags {}", flags);
+        }
+    }
+
+    #[test]
+    fn should_reject_corrupted_stream() {
+        let mut bad_crc = member(0, &FIXED, TEXT);
+        let len = bad_crc.len();
+        bad_crc[len - 8] ^= 1;
+        assert_eq!(decode_error(&bad_crc), "gzip stream has invalid checksum");
+
+        let mut bad_size = member(0, &FIXED, TEXT);
+        bad_size[len - 4] ^= 1;
+        assert_eq!(decode_error(&bad_size), "gzip stream has invalid length");
+
+        let mut bad_stored = member(0, &stored(TEXT, true), TEXT);
+        bad_stored[13] ^= 1;
+        assert_eq!(decode_error(&bad_stored), "gzip stream has corrupted stored block");
+
+        assert_eq!(decode_error(&member(0, &[0x07], b"")), "gzip stream has invalid block type");
+        assert_eq!(decode_error(b"plain text"), "not in gzip format");
+        let mut bad_method = member(0, &FIXED, TEXT);
+        bad_method[2] = 7;
+        assert_eq!(decode_error(&bad_method), "gzip stream uses unknown compression method");
+    }
+
+    #[test]
+    fn should_reject_truncated_stream() {
+        let stream = member(FLAG_NAME, &DYNAMIC, &lines());
+        for len in 0..stream.len() {
+            let error = decode(&stream[..len]).expect_err("to fail");
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof, "length {}", len);
+        }
+    }
+}
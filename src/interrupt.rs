@@ -0,0 +1,55 @@
+//!Handling of Ctrl-C.
+//!
+//!First interruption only sets flag, so that work in progress can be finished,
+//!while second one exits immediately.
+//!Available only on unix systems, elsewhere interruption terminates process as usual.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+///Exit code after interruption, same as set by shells for process killed by SIGINT.
+pub const EXIT_CODE: i32 = 130;
+
+static INTERRUPTS: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(unix)]
+mod sys {
+    use core::ffi::c_int;
+
+    pub const SIGINT: c_int = 2;
+
+    extern "C" {
+        pub fn signal(signum: c_int, handler: usize) -> usize;
+        pub fn _exit(status: c_int) -> !;
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_interrupt(_: core::ffi::c_int) {
+    //Only async-signal-safe operations are allowed here
+    if INTERRUPTS.fetch_add(1, Ordering::SeqCst) > 0 {
+        unsafe {
+            sys::_exit(EXIT_CODE)
+        }
+    }
+}
+
+#[cfg(unix)]
+///Installs handler of Ctrl-C.
+pub fn install() {
+    unsafe {
+        sys::signal(sys::SIGINT, on_interrupt as extern "C" fn(core::ffi::c_int) as usize);
+    }
+}
+
+#[cfg(not(unix))]
+///Installs handler of Ctrl-C.
+///
+///Not supported on this platform.
+pub fn install() {
+}
+
+#[inline(always)]
+///Returns whether Ctrl-C was pressed, after which no new work should be started.
+pub fn is_interrupted() -> bool {
+    INTERRUPTS.load(Ordering::Relaxed) > 0
+}
@@ -0,0 +1,892 @@
+//!XXHash utilities backing `xxhash` CLI.
+//!
+//!## Example
+//!
+//!```rust
+//!use xxhash_cli::{HashKind, HashResult, hash_reader};
+//!
+//!let hash = hash_reader(&HashKind::Xxh64, 0, "abc".as_bytes()).expect("to hash");
+//!assert_eq!(hash, HashResult::U64(xxhash_rust::xxh64::xxh64(b"abc", 0)));
+//!```
+
+#![warn(missing_docs)]
+#![allow(clippy::style)]
+
+use std::io::{self, Read};
+use std::time::{Duration, Instant};
+use std::collections::BTreeMap;
+
+pub mod state;
+pub mod glob;
+pub mod log;
+pub mod walk;
+pub mod output;
+pub mod check;
+pub mod vectors;
+
+///Interval of checking whether followed input has grown.
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+///Describes why file cannot be opened, telling apart common causes.
+pub struct OpenReason<'a>(pub &'a io::Error);
+
+impl core::fmt::Display for OpenReason<'_> {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.0.kind() {
+            io::ErrorKind::NotFound => fmt.write_str("no such file or directory"),
+            io::ErrorKind::PermissionDenied => fmt.write_str("permission denied"),
+            _ => write!(fmt, "{}", self.0),
+        }
+    }
+}
+
+///Default size of read buffer.
+pub const DEFAULT_BUFFER_SIZE: usize = 4096;
+
+///Observer of read retries, receiving error and number of attempt.
+type RetryObserver = Box<dyn FnMut(&io::Error, u32) + Send>;
+
+///Reader that yields input in chunks of up to buffer size.
+pub struct ChunkedReader<T> {
+    buffer: Vec<u8>,
+    ///Number of bytes left to read.
+    remaining: u64,
+    ///Number of times to retry read failed with transient error.
+    retries: u32,
+    ///Called before every retry.
+    on_retry: Option<RetryObserver>,
+    ///How long to wait for more data after end of input.
+    follow: Option<Duration>,
+    ///When end of input was reached without more data since.
+    idle_since: Option<Instant>,
+    io: T,
+}
+
+///Returns whether read failed with `kind` may succeed if retried.
+///
+///Such errors are reported by network file systems and non-blocking inputs.
+pub fn is_transient(kind: io::ErrorKind) -> bool {
+    matches!(kind, io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
+impl<T: Read> ChunkedReader<T> {
+    ///Creates new reader with buffer of `size` bytes.
+    ///
+    ///`size` must be non-zero.
+    pub fn new(io: T, size: usize) -> Self {
+        debug_assert_ne!(size, 0);
+
+        Self {
+            buffer: vec![0u8; size],
+            remaining: u64::MAX,
+            retries: 0,
+            on_retry: None,
+            follow: None,
+            idle_since: None,
+            io
+        }
+    }
+
+    ///Limits reader to yield at most `limit` bytes in total.
+    pub fn with_limit(mut self, limit: Option<u64>) -> Self {
+        self.remaining = limit.unwrap_or(u64::MAX);
+        self
+    }
+
+    ///Retries every read failed with transient error, as defined by `is_transient`, up to `retries` times.
+    ///
+    ///Delay before retry doubles with every attempt, starting at 10ms.
+    ///
+    ///```rust
+    ///use std::io::{self, Read};
+    ///use xxhash_cli::ChunkedReader;
+    ///
+    ///struct Flaky(bool);
+    ///
+    ///impl Read for Flaky {
+    ///    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    ///        self.0 = !self.0;
+    ///        match self.0 {
+    ///            true => Err(io::ErrorKind::TimedOut.into()),
+    ///            false => Ok(0),
+    ///        }
+    ///    }
+    ///}
+    ///
+    ///assert!(ChunkedReader::new(Flaky(false), 4).next().is_err());
+    ///assert!(ChunkedReader::new(Flaky(false), 4).with_retries(1).next().expect("to retry").is_none());
+    ///```
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    ///Calls `observe` with error and number of attempt, starting from 1, before every retry of failed read.
+    pub fn with_retry_observer(mut self, observe: impl FnMut(&io::Error, u32) + Send + 'static) -> Self {
+        self.on_retry = Some(Box::new(observe));
+        self
+    }
+
+    ///Keeps reading input after its end is reached, until no more data appears for `timeout`.
+    ///
+    ///Timeout restarts whenever new data is read, and data read so far is yielded without waiting for buffer to fill.
+    ///
+    ///```rust
+    ///use std::io::{self, Read};
+    ///use std::time::Duration;
+    ///use xxhash_cli::ChunkedReader;
+    ///
+    /////Grows by single byte on every other read
+    ///struct Growing(u8);
+    ///
+    ///impl Read for Growing {
+    ///    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    ///        self.0 += 1;
+    ///        match self.0 % 2 == 0 && self.0 <= 6 {
+    ///            true => {
+    ///                buf[0] = self.0;
+    ///                Ok(1)
+    ///            },
+    ///            false => Ok(0),
+    ///        }
+    ///    }
+    ///}
+    ///
+    ///fn read(timeout: Option<Duration>) -> Vec<u8> {
+    ///    let mut reader = ChunkedReader::new(Growing(0), 4).with_follow(timeout);
+    ///    let mut data = Vec::new();
+    ///    while let Some(chunk) = reader.next().expect("to read") {
+    ///        data.extend_from_slice(chunk);
+    ///    }
+    ///    data
+    ///}
+    ///
+    ///assert_eq!(read(None), []);
+    ///assert_eq!(read(Some(Duration::from_millis(200))), [2, 4, 6]);
+    ///```
+    pub fn with_follow(mut self, timeout: Option<Duration>) -> Self {
+        self.follow = timeout;
+        self
+    }
+
+    ///Gets next chunk, if any.
+    ///
+    ///Chunk is shorter than buffer only at the end of input, or when followed input stops growing.
+    ///
+    ///```rust
+    ///use xxhash_cli::ChunkedReader;
+    ///
+    ///fn chunks(len: usize, size: usize) -> Vec<usize> {
+    ///    let data = vec![1u8; len];
+    ///    let mut reader = ChunkedReader::new(data.as_slice(), size);
+    ///    let mut chunks = Vec::new();
+    ///    while let Some(chunk) = reader.next().expect("to read") {
+    ///        chunks.push(chunk.len());
+    ///    }
+    ///    chunks
+    ///}
+    ///
+    ///assert_eq!(chunks(0, 4), []);
+    ///assert_eq!(chunks(3, 4), [3]);
+    ///assert_eq!(chunks(4, 4), [4]);
+    ///assert_eq!(chunks(5, 4), [4, 1]);
+    ///assert_eq!(chunks(8, 4), [4, 4]);
+    ///```
+    pub fn next(&mut self) -> io::Result<Option<&[u8]>> {
+        let mut total_size = 0usize;
+        let mut buf = match usize::try_from(self.remaining) {
+            Ok(remaining) if remaining < self.buffer.len() => &mut self.buffer[..remaining],
+            _ => self.buffer.as_mut_slice(),
+        };
+        let mut attempt = 0;
+        //Stop once buffer is full, as empty read cannot be told apart from end of input
+        while !buf.is_empty() {
+            match self.io.read(buf) {
+                Ok(0) => match self.follow {
+                    //Data read so far is yielded, while waiting is resumed by next call
+                    Some(_) if total_size > 0 => break,
+                    Some(timeout) => {
+                        let idle = self.idle_since.get_or_insert_with(Instant::now).elapsed();
+                        match timeout.checked_sub(idle) {
+                            Some(remaining) if !remaining.is_zero() => std::thread::sleep(remaining.min(FOLLOW_POLL_INTERVAL)),
+                            _ => break,
+                        }
+                    },
+                    None => break,
+                },
+                Ok(size) => {
+                    self.idle_since = None;
+                    total_size = total_size.saturating_add(size);
+                    buf = &mut buf[size..];
+                    attempt = 0;
+                },
+                Err(error) => match error.kind() {
+                    io::ErrorKind::Interrupted => continue,
+                    kind if is_transient(kind) && attempt < self.retries => {
+                        if let Some(observe) = self.on_retry.as_mut() {
+                            observe(&error, attempt + 1);
+                        }
+                        std::thread::sleep(Duration::from_millis(10 << attempt.min(10)));
+                        attempt += 1;
+                    },
+                    _ => return Err(error)
+                },
+            }
+        }
+
+        if total_size == 0 {
+            Ok(None)
+        } else {
+            self.remaining -= total_size as u64;
+            Ok(Some(&self.buffer[..total_size]))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+///Hash algorithm.
+pub enum HashKind {
+    ///XXH3 128bit.
+    Xxh3,
+    ///Same as `Xxh3`, named as in reference implementation.
+    Xxh128,
+    ///XXH3 64bit.
+    ///
+    ///Computed by the same streaming state as 128bit variant, taking its 64bit digest,
+    ///which is identical to standalone XXH3 64bit function.
+    ///
+    ///```rust
+    ///use xxhash_cli::{HashKind, HashResult, hash_reader};
+    ///
+    ///let hash = hash_reader(&HashKind::Xxh3_64, 0, "abc".as_bytes()).expect("to hash");
+    ///assert_eq!(hash, HashResult::U64(0x78af5f94892f3950));
+    ///assert_eq!(hash, HashResult::U64(xxhash_rust::xxh3::xxh3_64(b"abc")));
+    ///
+    ///let hash = hash_reader(&HashKind::Xxh3_64, 1, "abc".as_bytes()).expect("to hash");
+    ///assert_eq!(hash, HashResult::U64(xxhash_rust::xxh3::xxh3_64_with_seed(b"abc", 1)));
+    ///```
+    Xxh3_64,
+    ///XXH64.
+    Xxh64,
+    ///XXH32.
+    Xxh32,
+}
+
+impl core::str::FromStr for HashKind {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.eq_ignore_ascii_case("xxh3") {
+            Ok(Self::Xxh3)
+        } else if text.eq_ignore_ascii_case("xxh128") {
+            Ok(Self::Xxh128)
+        } else if text.eq_ignore_ascii_case("xxh3_64") {
+            Ok(Self::Xxh3_64)
+        } else if text.eq_ignore_ascii_case("xxh32") {
+            Ok(Self::Xxh32)
+        } else if text.eq_ignore_ascii_case("xxh64") {
+            Ok(Self::Xxh64)
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl HashKind {
+    ///All supported algorithms.
+    pub const ALL: [Self; 5] = [Self::Xxh32, Self::Xxh64, Self::Xxh3_64, Self::Xxh3, Self::Xxh128];
+
+    ///Returns algorithm name, as accepted by `FromStr`.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Xxh3 => "xxh3",
+            Self::Xxh128 => "xxh128",
+            Self::Xxh3_64 => "xxh3_64",
+            Self::Xxh64 => "xxh64",
+            Self::Xxh32 => "xxh32",
+        }
+    }
+
+    ///Returns size of produced hash in bits.
+    pub const fn bits(&self) -> u32 {
+        match self {
+            Self::Xxh3 | Self::Xxh128 => 128,
+            Self::Xxh3_64 | Self::Xxh64 => 64,
+            Self::Xxh32 => 32,
+        }
+    }
+
+    ///Returns algorithm label, as used by reference implementation.
+    pub const fn label(&self) -> &'static str {
+        match self {
+            Self::Xxh3 | Self::Xxh128 => "XXH128",
+            Self::Xxh3_64 => "XXH3",
+            Self::Xxh64 => "XXH64",
+            Self::Xxh32 => "XXH32",
+        }
+    }
+
+    ///Returns algorithm with `label`, as used by reference implementation.
+    ///
+    ///`XXH128` refers to `Xxh128`, which is the same as `Xxh3`.
+    ///
+    ///```rust
+    ///use xxhash_cli::HashKind;
+    ///
+    ///for kind in [HashKind::Xxh32, HashKind::Xxh64, HashKind::Xxh3_64, HashKind::Xxh128] {
+    ///    assert_eq!(HashKind::from_label(kind.label()), Some(kind));
+    ///}
+    ///assert_eq!(HashKind::from_label("xxh64"), Some(HashKind::Xxh64));
+    ///assert_eq!(HashKind::from_label("MD5"), None);
+    ///```
+    pub fn from_label(label: &str) -> Option<Self> {
+        [Self::Xxh32, Self::Xxh64, Self::Xxh3_64, Self::Xxh128].into_iter().find(|kind| kind.label().eq_ignore_ascii_case(label))
+    }
+
+    #[inline(always)]
+    ///Returns whether algorithm uses custom secret.
+    pub const fn uses_secret(&self) -> bool {
+        matches!(self, Self::Xxh3 | Self::Xxh128 | Self::Xxh3_64)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+///Computed hash.
+pub enum HashResult {
+    ///32bit hash.
+    U32(u32),
+    ///64bit hash.
+    U64(u64),
+    ///128bit hash.
+    U128(u128),
+}
+
+impl HashResult {
+    #[inline(always)]
+    ///Returns hash value widened to 128bit.
+    pub const fn as_u128(&self) -> u128 {
+        match self {
+            Self::U32(hash) => *hash as u128,
+            Self::U64(hash) => *hash as u128,
+            Self::U128(hash) => *hash,
+        }
+    }
+
+    #[inline(always)]
+    ///Returns number of hex digits required to represent hash.
+    ///
+    ///It is 8 for `Xxh32`, 16 for `Xxh64` and `Xxh3_64`, and 32 for `Xxh3` and `Xxh128`.
+    ///Hex output is always padded with zeros to this width.
+    ///
+    ///```rust
+    ///use xxhash_cli::HashResult;
+    ///
+    ///let cases = [
+    ///    (HashResult::U32(0xA), "0000000a"),
+    ///    (HashResult::U32(0), "00000000"),
+    ///    (HashResult::U32(u32::MAX), "ffffffff"),
+    ///    (HashResult::U64(0xA), "000000000000000a"),
+    ///    (HashResult::U64(u64::MAX), "ffffffffffffffff"),
+    ///    (HashResult::U128(0xA), "0000000000000000000000000000000a"),
+    ///    (HashResult::U128(u128::MAX), "ffffffffffffffffffffffffffffffff"),
+    ///];
+    ///for (hash, hex) in cases {
+    ///    assert_eq!(hash.to_hex(false), hex);
+    ///    assert_eq!(hash.to_hex(false).len(), hash.hex_width());
+    ///}
+    ///```
+    pub const fn hex_width(&self) -> usize {
+        match self {
+            Self::U32(_) => 8,
+            Self::U64(_) => 16,
+            Self::U128(_) => 32,
+        }
+    }
+
+    ///Formats hash as zero-padded hex string.
+    ///
+    ///This is hex of canonical big-endian bytes, identical to output of reference `xxhsum`.
+    ///
+    ///```rust
+    ///use xxhash_cli::{HashKind, hash_reader};
+    ///
+    ///let hex = |kind| hash_reader(&kind, 0, "".as_bytes()).expect("to hash").to_hex(false);
+    ///assert_eq!(hex(HashKind::Xxh32), "02cc5d05");
+    ///assert_eq!(hex(HashKind::Xxh64), "ef46db3751d8e999");
+    ///assert_eq!(hex(HashKind::Xxh3_64), "2d06800538d394c2");
+    ///assert_eq!(hex(HashKind::Xxh128), "99aa06d3014798d86001c324468d497f");
+    ///```
+    pub fn to_hex(&self, uppercase: bool) -> String {
+        match uppercase {
+            true => format!("{:01$X}", self.as_u128(), self.hex_width()),
+            false => format!("{:01$x}", self.as_u128(), self.hex_width()),
+        }
+    }
+
+    ///Returns canonical big-endian representation of the hash.
+    pub fn to_be_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::U32(hash) => hash.to_be_bytes().to_vec(),
+            Self::U64(hash) => hash.to_be_bytes().to_vec(),
+            Self::U128(hash) => hash.to_be_bytes().to_vec(),
+        }
+    }
+
+    ///Formats canonical big-endian representation of the hash as base64.
+    ///
+    ///If `url` is specified, uses URL safe alphabet without padding, otherwise standard alphabet with padding.
+    ///
+    ///```rust
+    ///use xxhash_cli::HashResult;
+    ///
+    ///let cases = [
+    ///    (HashResult::U32(0), "AAAAAA==", "AAAAAA"),
+    ///    (HashResult::U32(0xfbeffeff), "++/+/w==", "--_-_w"),
+    ///    (HashResult::U64(0xef46db3751d8e999), "70bbN1HY6Zk=", "70bbN1HY6Zk"),
+    ///    (HashResult::U64(0xfbefbefbefbefbef), "++++++++++8=", "----------8"),
+    ///    (HashResult::U128(0x99aa06d3014798d86001c324468d497f), "maoG0wFHmNhgAcMkRo1Jfw==", "maoG0wFHmNhgAcMkRo1Jfw"),
+    ///    (HashResult::U128(0xbffbfffbefbefbefbe00000000ffff3e), "v/v/++++++++AAAAAP//Pg==", "v_v_--------AAAAAP__Pg"),
+    ///];
+    ///for (hash, standard, url) in cases {
+    ///    assert_eq!(hash.to_base64(false), standard);
+    ///    assert_eq!(hash.to_base64(true), url);
+    ///}
+    ///```
+    pub fn to_base64(&self, url: bool) -> String {
+        const STANDARD: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        const URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+        let alphabet = match url {
+            true => URL,
+            false => STANDARD,
+        };
+        let bytes = self.to_be_bytes();
+        let mut result = String::with_capacity(bytes.len().div_ceil(3) * 4);
+        for chunk in bytes.chunks(3) {
+            let block = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+            for idx in 0..4 {
+                if idx <= chunk.len() {
+                    result.push(alphabet[(block >> (18 - 6 * idx) & 0x3f) as usize] as char);
+                } else if !url {
+                    result.push('=');
+                }
+            }
+        }
+
+        result
+    }
+}
+
+///Size of custom secret required by streaming XXH3.
+pub const SECRET_SIZE: usize = 192;
+
+///Custom secret for XXH3.
+#[derive(Clone)]
+pub struct Secret(pub Box<[u8; SECRET_SIZE]>);
+
+impl Secret {
+    #[inline(always)]
+    ///Returns XXH3 64bit hash of the secret, identifying it without revealing it.
+    pub fn fingerprint(&self) -> u64 {
+        xxhash_rust::xxh3::xxh3_64(&*self.0)
+    }
+}
+
+///Parameters of hash algorithm.
+///
+///XXH3 variants with both seed and secret behave as reference `XXH3_128bits_withSecretandSeed`:
+///inputs up to 240 bytes are hashed with seed, while longer inputs are hashed with secret.
+///
+///```rust
+///use xxhash_cli::{HashKind, HashResult, Params, Secret, hash_with};
+///
+///let secret = Secret(Box::new(core::array::from_fn(|idx| idx as u8)));
+///let params = Params {
+///    seed: 1,
+///    secret: Some(secret.clone()),
+///};
+///let hash = |data: &[u8]| hash_with(&HashKind::Xxh3, &params, |update| Ok::<_, ()>(update(data))).expect("to hash");
+///
+///let short = [1u8; 240];
+///assert_eq!(hash(&short), HashResult::U128(xxhash_rust::xxh3::xxh3_128_with_seed(&short, 1)));
+///let long = [1u8; 241];
+///assert_eq!(hash(&long), HashResult::U128(xxhash_rust::xxh3::xxh3_128_with_secret(&long, &*secret.0)));
+///```
+#[derive(Clone)]
+pub struct Params {
+    ///Seed to use.
+    ///
+    ///Must fit 32bit for `Xxh32`, otherwise higher bits are ignored.
+    pub seed: u64,
+    ///Custom secret for XXH3 variants, combined with seed.
+    pub secret: Option<Secret>,
+}
+
+///Streaming hasher of any supported algorithm.
+pub trait StreamHasher {
+    ///Hashes provided chunk.
+    fn update(&mut self, input: &[u8]);
+    ///Computes hash of all data hashed so far.
+    fn finish(&self) -> HashResult;
+    ///Resets hasher to initial state, keeping seed and secret.
+    fn reset(&mut self);
+}
+
+struct Xxh3_128(xxhash_rust::xxh3::Xxh3);
+
+impl StreamHasher for Xxh3_128 {
+    #[inline(always)]
+    fn update(&mut self, input: &[u8]) {
+        self.0.update(input)
+    }
+
+    #[inline(always)]
+    fn finish(&self) -> HashResult {
+        HashResult::U128(self.0.digest128())
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.0.reset()
+    }
+}
+
+///XXH3 64bit, sharing streaming state with 128bit variant.
+struct Xxh3_64(xxhash_rust::xxh3::Xxh3);
+
+impl StreamHasher for Xxh3_64 {
+    #[inline(always)]
+    fn update(&mut self, input: &[u8]) {
+        self.0.update(input)
+    }
+
+    #[inline(always)]
+    fn finish(&self) -> HashResult {
+        HashResult::U64(self.0.digest())
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.0.reset()
+    }
+}
+
+struct Xxh64 {
+    hasher: xxhash_rust::xxh64::Xxh64,
+    seed: u64,
+}
+
+impl StreamHasher for Xxh64 {
+    #[inline(always)]
+    fn update(&mut self, input: &[u8]) {
+        self.hasher.update(input)
+    }
+
+    #[inline(always)]
+    fn finish(&self) -> HashResult {
+        HashResult::U64(self.hasher.digest())
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.hasher.reset(self.seed)
+    }
+}
+
+struct Xxh32 {
+    hasher: xxhash_rust::xxh32::Xxh32,
+    seed: u32,
+}
+
+impl StreamHasher for Xxh32 {
+    #[inline(always)]
+    fn update(&mut self, input: &[u8]) {
+        self.hasher.update(input)
+    }
+
+    #[inline(always)]
+    fn finish(&self) -> HashResult {
+        HashResult::U32(self.hasher.digest())
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.hasher.reset(self.seed)
+    }
+}
+
+fn xxh3(params: &Params) -> xxhash_rust::xxh3::Xxh3 {
+    let builder = xxhash_rust::xxh3::Xxh3Builder::new().with_seed(params.seed);
+    match params.secret.as_ref() {
+        //Zero seed with secret is the same as secret alone
+        Some(secret) => builder.with_secret(*secret.0).build(),
+        None => builder.build(),
+    }
+}
+
+impl HashKind {
+    ///Creates new streaming hasher for the algorithm.
+    pub fn hasher(&self, params: &Params) -> Box<dyn StreamHasher + Send> {
+        match self {
+            Self::Xxh3 | Self::Xxh128 => Box::new(Xxh3_128(xxh3(params))),
+            Self::Xxh3_64 => Box::new(Xxh3_64(xxh3(params))),
+            Self::Xxh64 => Box::new(Xxh64 {
+                hasher: xxhash_rust::xxh64::Xxh64::new(params.seed),
+                seed: params.seed,
+            }),
+            Self::Xxh32 => Box::new(Xxh32 {
+                hasher: xxhash_rust::xxh32::Xxh32::new(params.seed as u32),
+                seed: params.seed as u32,
+            }),
+        }
+    }
+}
+
+///Threshold of input size, up to which XXH3 with both seed and secret is computed using seed alone.
+const XXH3_MID_SIZE_MAX: usize = 240;
+
+///Computes hash of `input` at once, without setting up streaming hasher.
+///
+///Result is identical to hashing `input` with streaming hasher.
+///
+///```rust
+///use xxhash_cli::{HashKind, Params, Secret, hash_oneshot, hash_with};
+///
+///let secret = Secret(Box::new(core::array::from_fn(|idx| (idx * 7) as u8)));
+///let data = (0..2048).map(|idx| (idx * 31) as u8).collect::<Vec<_>>();
+///for kind in HashKind::ALL.iter() {
+///    for (seed, secret) in [(0, None), (3, None), (0, Some(secret.clone())), (3, Some(secret.clone()))] {
+///        let params = Params {
+///            seed,
+///            secret,
+///        };
+///        for len in [0, 1, 3, 4, 8, 9, 16, 17, 128, 129, 240, 241, 1024, 2048] {
+///            let input = &data[..len];
+///            let streaming = hash_with(kind, &params, |update| Ok::<_, ()>(update(input))).expect("to hash");
+///            assert_eq!(hash_oneshot(kind, &params, input), streaming, "{} of {} bytes", kind.name(), len);
+///        }
+///    }
+///}
+///```
+pub fn hash_oneshot(kind: &HashKind, params: &Params, input: &[u8]) -> HashResult {
+    use xxhash_rust::xxh3::{xxh3_128_with_seed, xxh3_128_with_secret, xxh3_64_with_seed, xxh3_64_with_secret};
+
+    //Same choice between seed and secret, as made by streaming hasher
+    let secret = match params.secret.as_ref() {
+        Some(_) if params.seed > 0 && input.len() <= XXH3_MID_SIZE_MAX => None,
+        secret => secret,
+    };
+    match (kind.uses_secret(), secret) {
+        (true, Some(secret)) => match kind {
+            HashKind::Xxh3_64 => HashResult::U64(xxh3_64_with_secret(input, &*secret.0)),
+            _ => HashResult::U128(xxh3_128_with_secret(input, &*secret.0)),
+        },
+        _ => match kind {
+            HashKind::Xxh3 | HashKind::Xxh128 => HashResult::U128(xxh3_128_with_seed(input, params.seed)),
+            HashKind::Xxh3_64 => HashResult::U64(xxh3_64_with_seed(input, params.seed)),
+            HashKind::Xxh64 => HashResult::U64(xxhash_rust::xxh64::xxh64(input, params.seed)),
+            HashKind::Xxh32 => HashResult::U32(xxhash_rust::xxh32::xxh32(input, params.seed as u32)),
+        },
+    }
+}
+
+///Computes hash over all data passed by `feed` to provided update function.
+pub fn hash_with<E>(kind: &HashKind, params: &Params, feed: impl FnOnce(&mut dyn FnMut(&[u8])) -> Result<(), E>) -> Result<HashResult, E> {
+    let mut hasher = kind.hasher(params);
+    feed(&mut |chunk| hasher.update(chunk))?;
+    Ok(hasher.finish())
+}
+
+///Computes hash of every kind over all data passed by `feed`, which is consumed only once.
+///
+///Results are in the same order as `kinds`.
+pub fn hash_many_with<E>(kinds: &[HashKind], params: &Params, feed: impl FnOnce(&mut dyn FnMut(&[u8])) -> Result<(), E>) -> Result<Vec<HashResult>, E> {
+    let mut hashers = kinds.iter().map(|kind| kind.hasher(params)).collect::<Vec<_>>();
+    feed(&mut |chunk| for hasher in hashers.iter_mut() {
+        hasher.update(chunk);
+    })?;
+    Ok(hashers.iter().map(|hasher| hasher.finish()).collect())
+}
+
+///Computes hash of directory tree from paths of files within it and their hashes.
+///
+///Paths must be relative to the directory, with components separated by `/` on every platform.
+///Files are sorted by UTF-8 bytes of their paths, and for each file its path, NUL byte and
+///canonical big-endian bytes of its hash are hashed in that order.
+///
+///```rust
+///use xxhash_cli::{HashKind, HashResult, Params, tree_hash};
+///
+///let params = Params {
+///    seed: 0,
+///    secret: None,
+///};
+///let hash = |files: &[(&str, HashResult)]| tree_hash(&HashKind::Xxh64, &params, files);
+///let (a, b) = (HashResult::U64(1), HashResult::U64(2));
+///
+///let tree = hash(&[("a", a), ("sub/b", b)]);
+///assert_eq!(tree, hash(&[("sub/b", b), ("a", a)]));
+///assert_ne!(tree, hash(&[("a", b), ("sub/b", a)]));
+///assert_ne!(tree, hash(&[("a", a), ("sub/c", b)]));
+///assert_ne!(tree, hash(&[("a", a)]));
+///```
+pub fn tree_hash(kind: &HashKind, params: &Params, files: &[(&str, HashResult)]) -> HashResult {
+    let mut files = files.iter().collect::<Vec<_>>();
+    files.sort_unstable_by(|left, right| left.0.as_bytes().cmp(right.0.as_bytes()));
+
+    let mut hasher = kind.hasher(params);
+    for (path, hash) in files {
+        hasher.update(path.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(&hash.to_be_bytes());
+    }
+    hasher.finish()
+}
+
+///Hashes `reader` until the end.
+pub fn hash_reader<R: Read>(kind: &HashKind, seed: u64, reader: R) -> io::Result<HashResult> {
+    hash_reader_with(kind, seed, reader, |_| ())
+}
+
+///Hashes `reader` until the end, passing every hashed chunk to `observe`.
+///
+///`observe` is called for every non-empty chunk, in order of reading, after chunk is hashed.
+///
+///```rust
+///use xxhash_cli::{HashKind, hash_reader, hash_reader_with};
+///
+///let mut size = 0;
+///let hash = hash_reader_with(&HashKind::Xxh3, 0, "abc".as_bytes(), |chunk| size += chunk.len()).expect("to hash");
+///assert_eq!(size, 3);
+///assert_eq!(hash, hash_reader(&HashKind::Xxh3, 0, "abc".as_bytes()).expect("to hash"));
+///```
+pub fn hash_reader_with<R: Read>(kind: &HashKind, seed: u64, reader: R, mut observe: impl FnMut(&[u8])) -> io::Result<HashResult> {
+    let params = Params {
+        seed,
+        secret: None,
+    };
+    let mut reader = ChunkedReader::new(reader, DEFAULT_BUFFER_SIZE);
+    hash_with(kind, &params, |update| {
+        while let Some(chunk) = reader.next()? {
+            update(chunk);
+            observe(chunk);
+        }
+        Ok(())
+    })
+}
+
+///Collector of results completed out of order, releasing them in order of their index.
+///
+///```rust
+///use xxhash_cli::Ordered;
+///
+///let mut released = Vec::new();
+///let mut ordered = Ordered::new();
+///for idx in [2, 0, 3, 1, 5, 4] {
+///    ordered.push(idx, idx * 10, |idx, value| released.push((idx, value)));
+///}
+///assert_eq!(released, [(0, 0), (1, 10), (2, 20), (3, 30), (4, 40), (5, 50)]);
+///assert!(ordered.is_empty());
+///```
+pub struct Ordered<T> {
+    ///Index of next result to release.
+    next: usize,
+    pending: BTreeMap<usize, T>,
+}
+
+impl<T> Ordered<T> {
+    ///Creates collector, expecting first result at index 0.
+    pub const fn new() -> Self {
+        Self {
+            next: 0,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    ///Adds result with `idx`, passing every result that is ready to `release`, in order.
+    ///
+    ///Every index must be pushed exactly once.
+    pub fn push(&mut self, idx: usize, value: T, mut release: impl FnMut(usize, T)) {
+        if idx != self.next {
+            self.pending.insert(idx, value);
+            return;
+        }
+
+        release(idx, value);
+        self.next += 1;
+        while let Some(value) = self.pending.remove(&self.next) {
+            release(self.next, value);
+            self.next += 1;
+        }
+    }
+
+    ///Returns whether there is no result waiting for preceding ones.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    ///Releases all results still waiting for preceding ones, in order of their index.
+    ///
+    ///Used when no more results are coming, e.g. when work is stopped early.
+    pub fn drain(&mut self, mut release: impl FnMut(usize, T)) {
+        for (idx, value) in core::mem::take(&mut self.pending) {
+            release(idx, value);
+        }
+    }
+}
+
+impl<T> Default for Ordered<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectors::*;
+
+    #[test]
+    fn should_hash_known_vectors_by_every_method() {
+        for (kind, input_idx, seed, is_secret, expected) in VECTORS {
+            let data = input(input_idx);
+            let params = params(seed, is_secret);
+            let name = format!("{} of input {} with seed {} and secret {}", kind.name(), input_idx, seed, is_secret);
+
+            assert_eq!(hash_oneshot(&kind, &params, &data).as_u128(), expected, "oneshot {}", name);
+            let streaming = hash_with(&kind, &params, |update| {
+                data.chunks(7).for_each(|chunk| update(chunk));
+                Ok::<_, ()>(())
+            });
+            assert_eq!(streaming.map(|hash| hash.as_u128()), Ok(expected), "streaming {}", name);
+
+            let mut hasher = kind.hasher(&params);
+            hasher.update(b"garbage");
+            hasher.reset();
+            hasher.update(&data);
+            assert_eq!(hasher.finish().as_u128(), expected, "reset {}", name);
+
+            if !is_secret {
+                assert_eq!(hash_reader(&kind, seed, data.as_slice()).expect("to hash").as_u128(), expected, "reader {}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn should_ignore_secret_outside_of_xxh3() {
+        for kind in [HashKind::Xxh32, HashKind::Xxh64] {
+            for seed in [0, SEED] {
+                let data = input(LONG);
+                assert_eq!(hash_oneshot(&kind, &params(seed, true), &data), hash_oneshot(&kind, &params(seed, false), &data));
+            }
+        }
+    }
+
+    #[test]
+    fn should_produce_result_of_algorithm_width() {
+        for kind in HashKind::ALL.iter() {
+            let hash = hash_oneshot(kind, &params(0, false), b"abc");
+            assert_eq!(hash.hex_width() * 4, kind.bits() as usize, "{}", kind.name());
+        }
+    }
+}
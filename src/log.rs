@@ -0,0 +1,75 @@
+//!Diagnostic events on stderr, filtered by `--log-level`.
+//!
+//!Events are written as single line of `key=value` pairs, starting with level and event name,
+//!with text values quoted as Rust strings.
+
+use core::fmt;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+///Verbosity of event.
+pub enum Level {
+    ///Failures.
+    Error,
+    ///Problems, that do not stop processing.
+    Warn,
+    ///Skipped inputs.
+    Info,
+    ///Progress of every input.
+    Debug,
+}
+
+impl Level {
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warn => "warn",
+            Self::Info => "info",
+            Self::Debug => "debug",
+        }
+    }
+}
+
+impl core::str::FromStr for Level {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.eq_ignore_ascii_case("error") {
+            Ok(Self::Error)
+        } else if text.eq_ignore_ascii_case("warn") {
+            Ok(Self::Warn)
+        } else if text.eq_ignore_ascii_case("info") {
+            Ok(Self::Info)
+        } else if text.eq_ignore_ascii_case("debug") {
+            Ok(Self::Debug)
+        } else {
+            Err(())
+        }
+    }
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Warn as u8);
+
+///Sets most verbose level of events to write.
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+#[inline(always)]
+///Returns whether events of `level` are written.
+pub fn is_enabled(level: Level) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+///Writes `event` with `fields`, if `level` is enabled.
+pub fn event(level: Level, event: &str, fields: &[(&str, &dyn fmt::Debug)]) {
+    if !is_enabled(level) {
+        return;
+    }
+
+    let mut line = format!("level={} event={}", level.name(), event);
+    for (key, value) in fields {
+        line.push_str(&format!(" {}={:?}", key, value));
+    }
+    eprintln!("{}", line);
+}
@@ -1,227 +1,2604 @@
-#![cfg_attr(feature = "cargo-clippy", allow(clippy::style))]
+#![allow(clippy::style)]
 
 use arg::Args;
+use xxhash_cli::{ChunkedReader, HashKind, HashResult, Ordered, Params, Secret, SECRET_SIZE, hash_many_with, hash_oneshot, hash_with, tree_hash};
+use xxhash_cli::state::State;
+use xxhash_cli::{OpenReason, check, glob, log, vectors, walk};
+use xxhash_cli::check::InputFormat;
+use xxhash_cli::output::{Encoding, Format, HashFormat, LineFormat, format_size, write_json_str};
 
-use std::io::{self, Read};
-use std::fs::File;
+mod mmap;
+mod gzip;
+mod interrupt;
+mod config;
+mod sparse;
 
-pub struct ChunkedReader<T, const N: usize> {
-    buffer: [u8; N],
-    io: T,
+use std::io::{self, Read, Write, BufRead, Seek, IsTerminal};
+use std::fs::{self, File};
+use std::path::{Component, Path, PathBuf};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Sort {
+    ///Order of arguments
+    None,
+    Name,
+    ///Hash of first algorithm, followed by failed files
+    Hash,
 }
 
-impl<T: Read, const N: usize> ChunkedReader<T, N> {
-    pub fn new(io: T) -> Self {
-        debug_assert_ne!(N, 0);
+impl core::str::FromStr for Sort {
+    type Err = ();
 
-        Self {
-            buffer: [0u8; N],
-            io
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.eq_ignore_ascii_case("none") {
+            Ok(Self::None)
+        } else if text.eq_ignore_ascii_case("name") {
+            Ok(Self::Name)
+        } else if text.eq_ignore_ascii_case("hash") {
+            Ok(Self::Hash)
+        } else {
+            Err(())
         }
     }
+}
 
-    ///Gets next chunk, if any.
-    pub fn next(&mut self) -> io::Result<Option<&[u8]>> {
-        let mut total_size = 0usize;
-        let mut buf = self.buffer.as_mut_slice();
-        loop {
-            match self.io.read(buf) {
-                Ok(0) => break,
-                Ok(size) => {
-                    total_size = total_size.saturating_add(size);
-                    buf = &mut buf[size..];
-                },
-                Err(error) => match error.kind() {
-                    io::ErrorKind::Interrupted => continue,
-                    _ => return Err(error)
-                },
-            }
+#[derive(Clone, Copy, Debug)]
+enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+        }
+    }
+}
+
+impl core::str::FromStr for LineEnding {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.eq_ignore_ascii_case("lf") {
+            Ok(Self::Lf)
+        } else if text.eq_ignore_ascii_case("crlf") {
+            Ok(Self::Crlf)
+        } else {
+            Err(())
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Color {
+    ///Colors output when stdout is terminal and `NO_COLOR` is not set.
+    Auto,
+    Always,
+    Never,
+}
+
+impl Color {
+    const GREEN: &'static str = "\x1b[32m";
+    const RED: &'static str = "\x1b[31m";
+    const YELLOW: &'static str = "\x1b[33m";
+    const RESET: &'static str = "\x1b[0m";
+
+    fn is_enabled(&self) -> bool {
+        match self {
+            Self::Auto => io::stdout().is_terminal() && std::env::var_os("NO_COLOR").map_or(true, |value| value.is_empty()),
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+
+    ///Wraps `text` into `code`, if colors are enabled.
+    fn paint<'a>(&self, code: &str, text: &'a str) -> Cow<'a, str> {
+        match self.is_enabled() {
+            true => format!("{}{}{}", code, text, Self::RESET).into(),
+            false => text.into(),
+        }
+    }
+}
+
+impl core::str::FromStr for Color {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.eq_ignore_ascii_case("auto") {
+            Ok(Self::Auto)
+        } else if text.eq_ignore_ascii_case("always") {
+            Ok(Self::Always)
+        } else if text.eq_ignore_ascii_case("never") {
+            Ok(Self::Never)
+        } else {
+            Err(())
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OutsideBase {
+    ///Refuses to hash files outside of base.
+    Error,
+    ///Prints absolute path with warning.
+    Absolute,
+}
+
+impl core::str::FromStr for OutsideBase {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.eq_ignore_ascii_case("error") {
+            Ok(Self::Error)
+        } else if text.eq_ignore_ascii_case("absolute") {
+            Ok(Self::Absolute)
+        } else {
+            Err(())
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Records {
+    ///Newline
+    Line,
+    ///NUL
+    Null,
+}
+
+impl Records {
+    const fn delimiter(&self) -> u8 {
+        match self {
+            Self::Line => b'\n',
+            Self::Null => 0,
         }
+    }
+}
+
+impl core::str::FromStr for Records {
+    type Err = ();
 
-        if total_size == 0 {
-            Ok(None)
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.eq_ignore_ascii_case("line") {
+            Ok(Self::Line)
+        } else if text.eq_ignore_ascii_case("null") {
+            Ok(Self::Null)
         } else {
-            Ok(Some(&mut self.buffer[..total_size]))
+            Err(())
         }
     }
 }
 
+#[derive(Clone, Copy, Debug)]
+enum Preset {
+    Manifest,
+    VerifyCi,
+}
 
-#[derive(Debug)]
-enum HashKind {
-    Xxh3,
-    Xxh3_64,
-    Xxh64,
-    Xxh32,
+impl Preset {
+    ///Returns defaults, that preset sets.
+    fn settings(&self) -> Vec<Setting> {
+        match self {
+            Self::Manifest => vec![Setting::Format(Format::Coreutils), Setting::Hex, Setting::Sort(Sort::Name)],
+            Self::VerifyCi => vec![Setting::Check, Setting::Quiet, Setting::FailFast],
+        }
+    }
 }
 
-impl core::str::FromStr for HashKind {
+impl core::str::FromStr for Preset {
     type Err = ();
 
     fn from_str(text: &str) -> Result<Self, Self::Err> {
-        if text.eq_ignore_ascii_case("xxh3") {
-            Ok(Self::Xxh3)
-        } else if text.eq_ignore_ascii_case("xxh3_64") {
-            Ok(Self::Xxh3_64)
-        } else if text.eq_ignore_ascii_case("xxh32") {
-            Ok(Self::Xxh32)
-        } else if text.eq_ignore_ascii_case("xxh64") {
-            Ok(Self::Xxh64)
+        if text.eq_ignore_ascii_case("manifest") {
+            Ok(Self::Manifest)
+        } else if text.eq_ignore_ascii_case("verify-ci") {
+            Ok(Self::VerifyCi)
         } else {
             Err(())
         }
     }
 }
 
-#[derive(Args, Debug)]
+///Default value of option, that applies unless the option is specified on command line.
+enum Setting {
+    Format(Format),
+    Color(Color),
+    BufferSize(usize),
+    Separator(String),
+    Hex,
+    Sort(Sort),
+    Check,
+    Quiet,
+    FailFast,
+}
+
+impl Setting {
+    ///Returns options of command line, any of which overrides setting.
+    const fn options(&self) -> &'static [&'static str] {
+        match self {
+            Self::Format(_) => &["--format", "--tag"],
+            Self::Color(_) => &["--color"],
+            Self::BufferSize(_) => &["--buffer-size"],
+            Self::Separator(_) => &["--separator"],
+            Self::Hex => &["--hex", "--decimal", "--canonical", "--base64", "--base64url", "--uuid", "--raw"],
+            Self::Sort(_) => &["--sort"],
+            Self::Check => &["--check", "-c"],
+            Self::Quiet => &["--quiet", "-q"],
+            Self::FailFast => &["--fail-fast"],
+        }
+    }
+
+    fn apply(self, args: &mut Cli) {
+        match self {
+            Self::Format(format) => args.format = format,
+            Self::Color(color) => args.color = color,
+            Self::BufferSize(size) => args.buffer_size = size,
+            Self::Separator(separator) => args.separator = separator,
+            Self::Hex => args.hex = true,
+            Self::Sort(sort) => args.sort = sort,
+            Self::Check => args.check = true,
+            Self::Quiet => args.quiet = true,
+            Self::FailFast => args.fail_fast = true,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+///Non-empty list of algorithms, separated by comma.
+struct KindList(Vec<HashKind>);
+
+impl core::ops::Deref for KindList {
+    type Target = [HashKind];
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl core::str::FromStr for KindList {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        text.split(',').map(str::parse).collect::<Result<Vec<_>, _>>().map(Self)
+    }
+}
+
+#[derive(Args, Clone, Debug)]
 ///xxhash
 ///Hashsum utility
+///Defaults of algorithm, format, separator, buffer size and color are read from ~/.config/xxhash-cli/config.toml
 struct Cli {
-    #[arg(short = "s", long = "seed", default_value = "0")]
-    ///Seed for hash to use. Defaults to 0.
-    pub seed: u64,
+    #[arg(long = "version")]
+    ///Prints version of the utility, version and enabled features of xxhash implementation, and supported algorithms.
+    pub version: bool,
+    #[arg(long = "list-algorithms")]
+    ///Prints supported algorithms with size of their hash in bits, separated by tab.
+    pub list_algorithms: bool,
+    #[arg(long = "self-test")]
+    ///Verifies every algorithm against built-in known hashes, with and without seed, failing on any mismatch.
+    pub self_test: bool,
+    #[arg(short = "s", long = "seed")]
+    ///Seed for hash to use, decimal or hex with 0x prefix. Use @path to read it from first line of file. Defaults to XXHASH_SEED if set, otherwise 0.
+    pub seed: Option<String>,
     #[arg(long, default_value = "false")]
-    ///Specifies to generate hash as UUID v4 for xxh3 128bit variant.
+    ///Specifies to generate hash as UUID v4. Requires 128bit variant: xxh3 or xxh128.
     pub uuid: bool,
+    #[arg(short = "v", long = "verbose")]
+    ///Appends algorithm, seed and fingerprint of custom secret to each hash in default format. Fingerprint is xxh3_64 hash of secret in hex.
+    pub verbose: bool,
+    #[arg(long = "hex")]
+    ///Formats hash as zero-padded hex string: 8 digits for xxh32, 16 for xxh64 and xxh3_64, 32 for xxh3 and xxh128.
+    pub hex: bool,
+    #[arg(long = "decimal")]
+    ///Formats hash as decimal integer. It is default, unless XXHASH_OUTPUT is set to hex or base64, which any encoding option overrides.
+    pub decimal: bool,
+    #[arg(long = "width")]
+    ///Pads hex hashes with leading zeros to specified number of digits, e.g. 32 for every algorithm. Only changes representation, not the value.
+    pub width: Option<usize>,
+    #[arg(long = "canonical")]
+    ///Formats hash as reference xxhsum does: hex of big-endian bytes. Default output is decimal value of the same integer.
+    pub canonical: bool,
+    #[arg(long = "uppercase")]
+    ///Uses uppercase digits in hex output.
+    pub uppercase: bool,
+    #[arg(short = "o", long = "output")]
+    ///Writes hashes to specified file instead of stdout, using coreutils format unless --format is specified.
+    pub output: Option<String>,
+    #[arg(long = "append")]
+    ///Appends to --output file instead of overwriting it.
+    pub append: bool,
+    #[arg(long = "no-names")]
+    ///Prints only hashes, without file names. Prefixed by algorithm name if there are multiple.
+    pub no_names: bool,
+    #[arg(short = "z", long = "zero")]
+    ///Ends each output line with NUL instead of newline.
+    pub zero: bool,
+    #[arg(long = "line-ending", default_value = "LineEnding::Lf")]
+    ///Line ending of textual output: lf or crlf. Checksum files with either are accepted by --check.
+    pub line_ending: LineEnding,
+    #[arg(long = "compare")]
+    ///Compares hashes of two files, exiting with 0 only if they are identical.
+    pub compare: bool,
+    #[arg(long = "separator", default_value = "\":\".to_owned()")]
+    ///Separator between file and hash in default format. Use \\t for tab. Defaults to colon.
+    pub separator: String,
+    #[arg(long = "expect")]
+    ///Compares hash of the single input against expected decimal or hex value. With -, expected value is read from stdin, either bare or as line of output.
+    pub expect: Option<String>,
+    #[arg(long = "sort", default_value = "Sort::None")]
+    ///Orders results of files by: none (order of arguments), name or hash. Results are buffered until all files are hashed, keeping them in memory. Cannot be used with --ndjson.
+    pub sort: Sort,
+    #[arg(long = "ndjson")]
+    ///Prints each result as JSON object on its own line as soon as it is ready. With multiple jobs, in order of completion.
+    pub ndjson: bool,
+    #[arg(long = "batch")]
+    ///Prints compact record per file for bulk ingestion: hashed size, modification time in seconds since Unix epoch (- if unknown), hash and path, separated by tab and ended by NUL. Path goes last, so that it may contain tabs and newlines.
+    pub batch: bool,
+    #[arg(long = "find-duplicates")]
+    ///Prints only files with identical size and hashes, in groups separated by empty line, once every file is hashed. xxhash is not cryptographic and contents are not compared, so verify them, e.g. with cmp, before removing duplicates.
+    pub find_duplicates: bool,
+    #[arg(long = "raw")]
+    ///Writes raw hash bytes without file names, big-endian for 32/64bit and little-endian for 128bit hashes.
+    pub raw: bool,
+    #[arg(long = "json")]
+    ///Prints results as JSON array. Hash is hex string, while errors are reported via error field.
+    pub json: bool,
+    #[arg(long = "name")]
+    ///Name to show for stdin in output instead of '-'. Only a label, it is never opened.
+    pub name: Option<String>,
+    #[arg(long = "relative-to")]
+    ///Prints paths relative to directory, so that checksum files can be moved together with it. Symbolic links are not resolved.
+    pub relative_to: Option<String>,
+    #[arg(long = "outside-base", default_value = "OutsideBase::Error")]
+    ///Handles files outside of --relative-to directory: error to refuse hashing or absolute to print absolute path with warning. Defaults to error.
+    pub outside_base: OutsideBase,
+    #[arg(short = "S", long = "string")]
+    ///Hashes UTF-8 bytes of the string exactly as passed, without trailing newline.
+    pub string: Vec<String>,
+    #[arg(long = "files-from")]
+    ///Reads newline delimited list of files to hash from specified file or stdin if '-'. Line may start with seed and space to override --seed.
+    pub files_from: Option<String>,
+    #[arg(long = "records")]
+    ///Splits stdin into records ending with delimiter: line (newline) or null (NUL), printing hash of each record named by its number as soon as it ends. Delimiter is not hashed.
+    pub records: Option<Records>,
+    #[arg(short = "0", long = "from-null")]
+    ///Reads NUL delimited list of files to hash from stdin, e.g. produced by `find -print0`.
+    pub from_null: bool,
+    #[arg(long = "include-names")]
+    ///Hashes name of each file as shown in output, in UTF-8 followed by NUL byte, before its content. Directory components are included as specified.
+    pub include_names: bool,
+    #[arg(long = "total")]
+    ///Prints hash of each file, followed by hash of all files as single input labeled TOTAL, same as --combined. Files are hashed one by one.
+    pub total: bool,
+    #[arg(long = "tree-hash")]
+    ///Prints single hash of every specified directory. Files within are sorted by path relative to it with / separator, then each path and hash of its file are hashed. Empty directories are not covered.
+    pub tree_hash: bool,
+    #[arg(long = "combined")]
+    ///Hashes all files as single input in order of arguments. Reordering files changes the hash.
+    pub combined: bool,
+    #[arg(long = "progress")]
+    ///Reports progress of hashing regular files to stderr, if stdout is terminal. Ignored with multiple jobs.
+    pub progress: bool,
+    #[arg(long = "force-progress")]
+    ///Reports progress even if stdout is not terminal.
+    pub force_progress: bool,
+    #[arg(long = "benchmark")]
+    ///Reports time spent opening, reading and hashing each file and its throughput to stderr.
+    pub benchmark: bool,
+    #[arg(long = "base64")]
+    ///Formats big-endian hash bytes as base64 string.
+    pub base64: bool,
+    #[arg(long = "base64url")]
+    ///Formats big-endian hash bytes as URL safe base64 string without padding.
+    pub base64url: bool,
+    #[arg(short = "r", long = "recursive")]
+    ///Hashes every regular file within specified directories.
+    pub recursive: bool,
+    #[arg(short = "q", long = "quiet")]
+    ///Prints only failures to stderr, leaving outcome to exit code. With --json or --ndjson, prints only entries of failed files.
+    pub quiet: bool,
+    #[arg(long = "exclude")]
+    ///Skips files and directories matching wildcard pattern within directories walked by --recursive. Patterns with / match path relative to specified directory, others match name.
+    pub exclude: Vec<String>,
+    #[arg(long = "max-depth")]
+    ///Limits how deep --recursive descends, 0 hashes only files directly within specified directory. Followed symbolic links count as directories. Unlimited by default.
+    pub max_depth: Option<usize>,
+    #[arg(long = "follow-symlinks")]
+    ///Follows symbolic links within directories walked by --recursive, instead of skipping them.
+    pub follow_symlinks: bool,
+    #[arg(long = "glob")]
+    ///Expands wildcards in file arguments, for shells that do not do it.
+    pub glob: bool,
+    #[arg(long = "show-size")]
+    ///Appends number of hashed bytes to each hash, after separator in default format and after space otherwise.
+    pub show_size: bool,
+    #[arg(long = "summary")]
+    ///Prints number of hashed files, their total size and time taken to stderr once done.
+    pub summary: bool,
+    #[arg(long = "error-summary")]
+    ///Prints numbers of opened, hashed, failed and skipped inputs as JSON object once done. Goes to stdout as last entry with --json or --ndjson, or to stderr otherwise.
+    pub error_summary: bool,
+    #[arg(long = "dry-run")]
+    ///Lists files that would be hashed, one per line, without reading them.
+    pub dry_run: bool,
+    #[arg(long = "fail-fast")]
+    ///Stops at first file that cannot be hashed, instead of continuing with the rest. With --check, stops at first failed checksum. With multiple jobs, files being hashed are finished, but no new ones are started.
+    pub fail_fast: bool,
+    #[arg(long = "preset")]
+    ///Sets defaults of bundle of options, which are overridden by ones specified explicitly: manifest is --format coreutils --hex --sort name, verify-ci is --check --quiet --fail-fast. Can be specified as --preset=<name>.
+    pub preset: Vec<Preset>,
+    #[arg(short = "j", long = "jobs", default_value = "1")]
+    ///Number of files to hash concurrently. Defaults to 1. Results are printed in order of files as soon as preceding ones are done, except for --ndjson.
+    pub jobs: usize,
+    #[arg(long = "offset", default_value = "0")]
+    ///Number of bytes to skip at the beginning of each file. Defaults to 0.
+    pub offset: u64,
+    #[arg(long = "length")]
+    ///Maximum number of bytes to hash from each file, starting at offset.
+    pub length: Option<u64>,
+    #[arg(long = "save-state")]
+    ///Saves hashing state of single input to specified file periodically and once done, so hashing can be resumed. Supported only by xxh32 and xxh64.
+    pub save_state: Option<String>,
+    #[arg(long = "resume-state")]
+    ///Continues hashing from state saved by --save-state, skipping already hashed bytes in addition to --offset.
+    pub resume_state: Option<String>,
+    #[arg(long = "detect-changes")]
+    ///Warns when number of bytes read differs from file size before hashing, e.g. as file is being written.
+    pub detect_changes: bool,
+    #[arg(long = "special")]
+    ///Allows hashing of special files, like named pipes and devices, which may never end.
+    pub special: bool,
+    #[arg(long = "device")]
+    ///Allows hashing block devices, like /dev/sdb, reading them to the end. Supported on Unix and typically requires root privileges.
+    pub device: bool,
+    #[arg(long = "decompress")]
+    ///Decompresses gzip input, hashing decompressed content instead of file as it is.
+    pub decompress: bool,
+    #[arg(long = "head")]
+    ///Hashes only first specified number of bytes of each file, for quick comparison. Digest differs from digest of whole file.
+    pub head: Option<u64>,
+    #[arg(long = "mmap")]
+    ///Memory maps regular files instead of reading them in chunks. Produces identical hash.
+    pub mmap: bool,
+    #[arg(long = "follow")]
+    ///Keeps reading regular files after their end, like tail -f, until they stop growing for --follow-timeout.
+    pub follow: bool,
+    #[arg(long = "follow-timeout", default_value = "1000")]
+    ///Milliseconds without new data after end of file, that --follow waits before finishing file. Restarts whenever file grows. Defaults to 1000.
+    pub follow_timeout: u64,
+    #[arg(long = "sparse")]
+    ///Skips reading holes of sparse regular files, hashing zeros in their place. Produces identical hash. Files are read as usual where holes cannot be detected.
+    pub sparse: bool,
+    #[arg(long = "log-level", default_value = "log::Level::Warn")]
+    ///Verbosity of diagnostics on stderr: error, warn, info (skipped files) or debug (opened and hashed files, sizes). Defaults to warn.
+    pub log_level: log::Level,
+    #[arg(long = "retries", default_value = "0")]
+    ///Number of times to retry read that timed out or would block, e.g. on network file systems. Other errors are not retried. Defaults to 0.
+    pub retries: u32,
+    #[arg(long = "buffer-size", default_value = "4096")]
+    ///Size of read buffer in bytes. Defaults to 4096.
+    pub buffer_size: usize,
+    #[arg(long = "secret")]
+    ///Custom secret for xxh3 variants. Either path to file or hex string of exactly 192 bytes, as streaming hasher supports only secret of default size and cutting longer secret would change hashes. With --seed, inputs up to 240 bytes are hashed with seed instead.
+    pub secret: Option<String>,
+    #[arg(short = "c", long = "check")]
+    ///Reads checksums from specified files and verifies them. Directories are searched for .xxh and .xxh3 files.
+    pub check: bool,
+    #[arg(long = "tag")]
+    ///Prints hashes in bsd format, same as --format bsd.
+    pub tag: bool,
+    #[arg(long = "label")]
+    ///Replaces algorithm label in bsd format, such as XXH128. Cannot contain whitespace, parentheses or =. With --check, lines with this label are verified by specified algorithm.
+    pub label: Option<String>,
+    #[arg(long = "input-format", default_value = "InputFormat::Auto")]
+    ///Format of checksum lines read by --check: coreutils (hash  file), bsd (LABEL (file) = hash), colon (file:hash) or auto to detect it for every line. Defaults to auto.
+    pub input_format: InputFormat,
+    #[arg(long = "ignore-missing")]
+    ///With --check, skips files that do not exist instead of failing. Checksum file, that lists only missing files, still fails, as it verifies nothing.
+    pub ignore_missing: bool,
+    #[arg(long = "strict")]
+    ///With --check, fails on improperly formatted checksum lines instead of skipping them.
+    pub strict: bool,
+    #[arg(long = "only-failed")]
+    ///With --check, omits OK lines, but unlike --quiet still prints summary.
+    pub only_failed: bool,
+    #[arg(long = "color", default_value = "Color::Auto")]
+    ///Colors verification results: auto, always or never. Auto colors only terminal output, unless NO_COLOR is set.
+    pub color: Color,
+    #[arg(long = "format", default_value = "Format::Default")]
+    ///Output format: default (file:hash), coreutils (hash  file) or bsd (LABEL (file) = hash).
+    pub format: Format,
     #[arg(required)]
-    ///Hash algorithm to use
-    pub kind: HashKind,
-    ///File to hash
+    ///Hash algorithm to use, or comma separated list to compute in single pass. [default: xxh3, overridden by XXHASH_DEFAULT or config file]
+    pub kind: KindList,
+    ///File to hash. Reads stdin when no file or '-' is specified. Files are hashed in order: arguments, --files-from, --from-null, then stdin if '-' is specified.
     pub file: Vec<String>,
 }
 
-fn open_file(path: &str) -> io::Result<ChunkedReader<File, 4096>> {
-    Ok(ChunkedReader::new(File::open(path)?))
+///Name of the input that refers to stdin.
+const STDIN: &str = "-";
+///Name of the output for combined hash of all files.
+const COMBINED: &str = "(combined)";
+///Name of the output for combined hash, printed after hashes of files.
+const TOTAL: &str = "TOTAL";
+///Name of the stdout in error messages.
+const STDOUT: &str = "stdout";
+///Size of file up to which it is read into memory at once.
+const SMALL_FILE_SIZE: u64 = 64 * 1024;
+///Number of hashed bytes after which state is saved again.
+const STATE_SAVE_INTERVAL: u64 = 64 * 1024 * 1024;
+
+enum Input {
+    File(ChunkedReader<File>),
+    Stdin(ChunkedReader<io::StdinLock<'static>>),
+    ///Decompressed content of gzip file or stdin.
+    Gzip(ChunkedReader<Box<gzip::Decoder<Box<dyn Read>>>>),
+    ///In-memory data, yielded as single chunk.
+    Bytes {
+        data: Vec<u8>,
+        is_consumed: bool,
+    },
+    ///Memory mapped file, range of which is yielded as single chunk.
+    Mmap {
+        map: mmap::Mmap,
+        range: core::ops::Range<usize>,
+        is_consumed: bool,
+    },
+    ///Regular file read with `--sparse`.
+    Sparse(sparse::SparseReader),
 }
 
-fn main() {
-    let args = arg::parse_args::<Cli>();
+impl Input {
+    #[inline(always)]
+    fn next(&mut self) -> io::Result<Option<&[u8]>> {
+        match self {
+            Self::File(reader) => reader.next(),
+            Self::Stdin(reader) => reader.next(),
+            Self::Gzip(reader) => reader.next(),
+            Self::Sparse(reader) => reader.next(),
+            Self::Bytes { data, is_consumed } => if *is_consumed {
+                Ok(None)
+            } else {
+                *is_consumed = true;
+                Ok(Some(data.as_slice()))
+            },
+            Self::Mmap { map, range, is_consumed } => if *is_consumed || range.start == range.end {
+                Ok(None)
+            } else {
+                *is_consumed = true;
+                Ok(Some(&map.as_slice()[range.clone()]))
+            },
+        }
+    }
+}
 
-    if args.file.is_empty() {
-        println!("No file specified...");
-        return;
+///Creates observer of read retries, logging them for `path`.
+fn log_retry(path: &str) -> impl FnMut(&io::Error, u32) + Send + 'static {
+    let path = path.to_owned();
+    move |error, attempt| log::event(log::Level::Warn, "retrying", &[("file", &path), ("attempt", &attempt), ("error", &error.to_string())])
+}
+
+///Opens input for reading.
+///
+///If `--mmap` is set, regular non-empty files are memory mapped, falling back to streaming
+///read when mapping is not possible.
+///Both approaches produce identical hash.
+///With `--sparse`, holes of regular files are not read.
+///
+///Input is limited to the range specified by `--offset` and `--length`.
+///With `--decompress` the range applies to decompressed content.
+fn open_file(path: &str, args: &Cli) -> io::Result<Input> {
+    if args.decompress {
+        return open_gzip(path, args);
     }
 
-    match args.kind {
-        HashKind::Xxh3 => {
-            let mut hasher = xxhash_rust::xxh3::Xxh3::with_seed(args.seed);
-            for file in args.file.iter() {
-                let mut reader = match open_file(file) {
-                    Ok(file) => file,
-                    Err(error) => {
-                        eprintln!("{}: cannot open: {}", file, error);
-                        return;
-                    }
-                };
+    if path == STDIN {
+        let mut stdin = io::stdin().lock();
+        if args.offset > 0 {
+            let skipped = io::copy(&mut (&mut stdin).take(args.offset), &mut io::sink())?;
+            if skipped < args.offset {
+                return Err(offset_error(args.offset, skipped));
+            }
+        }
+        return Ok(Input::Stdin(ChunkedReader::new(stdin, args.buffer_size).with_limit(args.length).with_retries(args.retries).with_retry_observer(log_retry(path))));
+    }
 
-                loop {
-                    match reader.next() {
-                        Ok(None) => break,
-                        Ok(Some(chunk)) => hasher.update(chunk),
-                        Err(error) => {
-                            eprintln!("{}: error reading: {}", file, error);
-                            return;
-                        }
-                    }
-                }
+    //Opening named pipe blocks until writer appears, so check type by path
+    check_file_type(&fs::metadata(path)?, args)?;
+    let mut file = File::open(path)?;
+    let meta = file.metadata()?;
+
+    if args.sparse && meta.is_file() {
+        if args.offset > meta.len() {
+            return Err(offset_error(args.offset, meta.len()));
+        }
+        let end = args.length.map_or(meta.len(), |length| args.offset.saturating_add(length).min(meta.len()));
+        return Ok(Input::Sparse(sparse::SparseReader::new(file, args.buffer_size, args.offset, end)));
+    }
 
-                let hash = hasher.digest128();
-                if args.uuid {
-                    let uuid = lolid::Uuid::from_bytes(hash.to_le_bytes()).set_variant().set_version(lolid::Version::Random);
-                    println!("{file}:{uuid}");
-                } else {
-                    println!("{file}:{hash}");
+    if args.mmap || args.offset > 0 {
+        if meta.is_file() && args.offset > meta.len() {
+            return Err(offset_error(args.offset, meta.len()));
+        }
+
+        if args.mmap && meta.is_file() && meta.len() > 0 {
+            if let Ok(len) = usize::try_from(meta.len()) {
+                if let Ok(map) = mmap::Mmap::map(&file, len) {
+                    //Offset fits due to check above
+                    let start = args.offset as usize;
+                    let end = match args.length {
+                        Some(length) => start.saturating_add(usize::try_from(length).unwrap_or(usize::MAX)).min(len),
+                        None => len,
+                    };
+                    return Ok(Input::Mmap {
+                        map,
+                        range: start..end,
+                        is_consumed: false,
+                    });
                 }
-                hasher.reset();
             }
-        },
-        HashKind::Xxh3_64 => {
-            let mut hasher = xxhash_rust::xxh3::Xxh3::with_seed(args.seed);
-            for file in args.file.iter() {
-                let mut reader = match open_file(file) {
-                    Ok(file) => file,
-                    Err(error) => {
-                        eprintln!("{}: cannot open: {}", file, error);
-                        return;
-                    }
-                };
+        }
 
-                loop {
-                    match reader.next() {
-                        Ok(None) => break,
-                        Ok(Some(chunk)) => hasher.update(chunk),
-                        Err(error) => {
-                            eprintln!("{}: error reading: {}", file, error);
-                            return;
-                        }
-                    }
+        if args.offset > 0 {
+            file.seek(io::SeekFrom::Start(args.offset))?;
+        }
+    }
+
+    //Reads small files at once, as syscalls dominate the time needed to hash them.
+    let remaining = meta.len().saturating_sub(args.offset).min(args.length.unwrap_or(u64::MAX));
+    //Reading at once gives up on first failed read, so it is not used when retries are requested
+    if meta.is_file() && remaining <= SMALL_FILE_SIZE && args.retries == 0 && !args.follow {
+        //Allow extra byte to detect end of file without reallocation
+        let mut data = Vec::with_capacity(remaining as usize + 1);
+        file.take(args.length.unwrap_or(u64::MAX)).read_to_end(&mut data)?;
+        return Ok(Input::Bytes {
+            data,
+            is_consumed: false,
+        });
+    }
+
+    let follow = (args.follow && meta.is_file()).then(|| Duration::from_millis(args.follow_timeout));
+    Ok(Input::File(ChunkedReader::new(file, args.buffer_size).with_limit(args.length).with_retries(args.retries).with_retry_observer(log_retry(path)).with_follow(follow)))
+}
+
+#[cfg(unix)]
+fn is_block_device(meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    meta.file_type().is_block_device()
+}
+
+#[cfg(not(unix))]
+fn is_block_device(_: &fs::Metadata) -> bool {
+    false
+}
+
+///Returns size of block device, which is not reported by its metadata.
+fn device_size(path: &str) -> io::Result<u64> {
+    File::open(path)?.seek(io::SeekFrom::End(0))
+}
+
+///Rejects files that cannot be hashed, unless they are allowed by `--special` or `--device`.
+fn check_file_type(meta: &fs::Metadata, args: &Cli) -> io::Result<()> {
+    //Opening directory succeeds on some platforms, but reading fails with obscure error
+    if meta.is_dir() {
+        Err(io::ErrorKind::IsADirectory.into())
+    } else if is_block_device(meta) && !args.device && !args.special {
+        Err(io::Error::other("is a block device, use --device to hash it"))
+    } else if !meta.is_file() && !is_block_device(meta) && !args.special {
+        //Pipes and devices may block forever or never end
+        Err(io::Error::other("not a regular file, use --special to hash it"))
+    } else {
+        Ok(())
+    }
+}
+
+fn open_gzip(path: &str, args: &Cli) -> io::Result<Input> {
+    let reader: Box<dyn Read> = match path {
+        STDIN => Box::new(io::stdin().lock()),
+        path => {
+            check_file_type(&fs::metadata(path)?, args)?;
+            Box::new(File::open(path)?)
+        }
+    };
+
+    let mut decoder = Box::new(gzip::Decoder::new(reader));
+    if args.offset > 0 {
+        let skipped = io::copy(&mut (&mut decoder).take(args.offset), &mut io::sink())?;
+        if skipped < args.offset {
+            return Err(offset_error(args.offset, skipped));
+        }
+    }
+    Ok(Input::Gzip(ChunkedReader::new(decoder, args.buffer_size).with_limit(args.length).with_retries(args.retries).with_retry_observer(log_retry(path))))
+}
+
+fn offset_error(offset: u64, size: u64) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("offset {} exceeds size {}", offset, size))
+}
+
+///Walks directory, collecting every regular file within in sorted order.
+fn walk_dir(dir: &Path, args: &Cli, files: &mut Vec<String>) {
+    let options = walk::WalkOptions {
+        exclude: &args.exclude,
+        max_depth: args.max_depth,
+        follow_symlinks: args.follow_symlinks,
+    };
+    walk::walk_dir(dir, &options, files, |error| eprintln!("{}", error));
+}
+
+///Reads NUL delimited list of files, skipping empty entries.
+fn read_null_delimited(reader: impl BufRead, files: &mut Vec<String>) -> io::Result<()> {
+    for entry in reader.split(b'\0') {
+        let entry = entry?;
+        if entry.is_empty() {
+            continue;
+        }
+
+        match String::from_utf8(entry) {
+            Ok(entry) => files.push(entry),
+            Err(error) => eprintln!("{}: path is not valid UTF-8", String::from_utf8_lossy(error.as_bytes())),
+        }
+    }
+
+    Ok(())
+}
+
+///Returns whether `text` looks like seed, rather than part of file name.
+fn is_seed_like(text: &str) -> bool {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => !hex.is_empty() && hex.bytes().all(|byte| byte.is_ascii_hexdigit()),
+        None => !text.is_empty() && text.bytes().all(|byte| byte.is_ascii_digit()),
+    }
+}
+
+///Reads newline delimited list of files, skipping blank lines and `#` comments.
+///
+///Each line may start with seed separated by space, overriding global seed for the file.
+fn read_files_from(path: &str, kinds: &[HashKind], files: &mut Vec<String>, seeds: &mut HashMap<String, u64>) -> Result<(), String> {
+    let reader = open_lines(path).map_err(|error| format!("{}: {}", path, HashError::Open(error)))?;
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line.map_err(|error| format!("{}: {}", path, HashError::Read(error)))?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let file = match line.split_once(' ') {
+            Some((seed, file)) if is_seed_like(seed) => {
+                let seed = parse_seed(seed).map_err(|error| format!("{}:{}: {}", path, idx + 1, error))?;
+                if kinds.contains(&HashKind::Xxh32) && u32::try_from(seed).is_err() {
+                    return Err(format!("{}:{}: {} is not valid seed for 32bit hash", path, idx + 1, seed));
+                } else if file.is_empty() {
+                    return Err(format!("{}:{}: missing file after seed", path, idx + 1));
                 }
+                seeds.insert(file.to_owned(), seed);
+                file
+            },
+            _ => line,
+        };
+
+        files.push(file.to_owned());
+    }
+
+    Ok(())
+}
+
+///Replaces wildcard patterns with matching paths, leaving existing paths as they are.
+///
+///Patterns that match nothing are reported and counted in `unmatched`.
+fn expand_globs(inputs: Vec<String>, unmatched: &mut usize) -> Vec<String> {
+    let mut expanded = Vec::with_capacity(inputs.len());
+
+    for input in inputs {
+        if !glob::is_pattern(&input) || Path::new(&input).exists() {
+            expanded.push(input);
+            continue;
+        }
+
+        let paths = glob::expand(&input);
+        if paths.is_empty() {
+            eprintln!("{}: no files match pattern", input);
+            *unmatched += 1;
+        }
+        expanded.extend(paths);
+    }
+
+    expanded
+}
+
+///Expands list of inputs into list of files to hash, counting directories that are not hashed and patterns that match nothing in `rejected`.
+fn collect_files(inputs: Vec<String>, args: &Cli, rejected: &mut usize) -> Vec<String> {
+    let inputs = match args.glob {
+        true => expand_globs(inputs, rejected),
+        false => inputs,
+    };
+    let mut files = Vec::with_capacity(inputs.len());
+
+    for input in inputs {
+        if input != STDIN && Path::new(&input).is_dir() {
+            if args.recursive {
+                walk_dir(Path::new(&input), args, &mut files);
+            } else {
+                eprintln!("{}: is a directory", input);
+                log::event(log::Level::Info, "skipped", &[("path", &input), ("reason", &"directory")]);
+                *rejected += 1;
+            }
+        } else {
+            files.push(input);
+        }
+    }
+
+    files
+}
+
+///Hashes of single input.
+struct Digest {
+    ///Hash of every requested algorithm, in order of request.
+    hashes: Vec<HashResult>,
+    ///Number of bytes hashed.
+    size: u64,
+}
+
+///Feeds input until the end, returning number of fed bytes.
+fn feed_input(input: &mut Input, update: &mut dyn FnMut(&[u8])) -> io::Result<u64> {
+    let mut size = 0u64;
+    while let Some(chunk) = input.next()? {
+        update(chunk);
+        size = size.saturating_add(chunk.len() as u64);
+    }
+
+    Ok(size)
+}
+
+///Feeds name of `file` terminated by NUL, if `--include-names` is set.
+fn feed_name(args: &Cli, file: &str, update: &mut dyn FnMut(&[u8])) {
+    if args.include_names {
+        update(display_name(args, file).as_bytes());
+        update(&[0]);
+    }
+}
 
-                let hash = hasher.digest();
-                println!("{file}:{hash}");
+///Hashes every record of `input` ending with `delimiter` separately, passing hashes of each record to `release` once it ends.
+///
+///Stops once `release` returns false.
+///Records are streamed through hashers, so they are not limited by buffer size.
+fn hash_records(kinds: &[HashKind], params: &Params, input: &mut Input, delimiter: u8, mut release: impl FnMut(Digest) -> bool) -> io::Result<()> {
+    let mut hashers = kinds.iter().map(|kind| kind.hasher(params)).collect::<Vec<_>>();
+    let mut size = 0u64;
+    //Last record may lack delimiter
+    let mut is_pending = false;
+
+    while let Some(mut chunk) = input.next()? {
+        if interrupt::is_interrupted() {
+            break;
+        }
+
+        while let Some(end) = chunk.iter().position(|byte| *byte == delimiter) {
+            for hasher in hashers.iter_mut() {
+                hasher.update(&chunk[..end]);
+            }
+            let is_continue = release(Digest {
+                hashes: hashers.iter().map(|hasher| hasher.finish()).collect(),
+                size: size + end as u64,
+            });
+            if !is_continue {
+                return Ok(());
+            }
+            for hasher in hashers.iter_mut() {
                 hasher.reset();
             }
-        },
-        HashKind::Xxh64 => {
-            let mut hasher = xxhash_rust::xxh64::Xxh64::new(args.seed);
-            for file in args.file.iter() {
-                let mut reader = match open_file(file) {
-                    Ok(file) => file,
-                    Err(error) => {
-                        eprintln!("{}: cannot open: {}", file, error);
-                        return;
-                    }
-                };
+            size = 0;
+            is_pending = false;
+            chunk = &chunk[end + 1..];
+        }
 
-                loop {
-                    match reader.next() {
-                        Ok(None) => break,
-                        Ok(Some(chunk)) => hasher.update(chunk),
-                        Err(error) => {
-                            eprintln!("{}: error reading: {}", file, error);
-                            return;
-                        }
-                    }
-                }
+        if !chunk.is_empty() {
+            for hasher in hashers.iter_mut() {
+                hasher.update(chunk);
+            }
+            size += chunk.len() as u64;
+            is_pending = true;
+        }
+    }
+
+    if is_pending {
+        release(Digest {
+            hashes: hashers.iter().map(|hasher| hasher.finish()).collect(),
+            size,
+        });
+    }
+    Ok(())
+}
+
+///Hashes input until the end with every algorithm of `kinds`.
+fn hash_input(kinds: &[HashKind], params: &Params, input: &mut Input) -> io::Result<Digest> {
+    let mut size = 0;
+    let hashes = hash_many_with(kinds, params, |update| feed_input(input, update).map(|fed| size = fed))?;
+    Ok(Digest {
+        hashes,
+        size,
+    })
+}
+
+fn hash_string(kinds: &[HashKind], params: &Params, text: &str) -> io::Result<Digest> {
+    let mut input = Input::Bytes {
+        data: text.as_bytes().to_owned(),
+        is_consumed: false,
+    };
+    hash_input(kinds, params, &mut input)
+}
+
+///Hashes content of all `files` as single input, in the specified order.
+fn hash_combined<'a>(args: &Cli, params: &FileParams, files: &'a [String]) -> Result<Digest, (&'a str, HashError)> {
+    let mut size = 0u64;
+    let hashes = hash_many_with(&args.kind, &params.global, |update| {
+        for file in files.iter() {
+            let mut input = open_file(file, args).map_err(|error| (file.as_str(), HashError::Open(error)))?;
+            feed_name(args, file, update);
+            let fed = feed_input(&mut input, update).map_err(|error| (file.as_str(), HashError::Read(error)))?;
+            size = size.saturating_add(fed);
+        }
+        Ok(())
+    })?;
+    Ok(Digest {
+        hashes,
+        size,
+    })
+}
+
+///Computes hash of directory tree, as defined by `tree_hash`, taking files as walked by `--recursive`.
+fn hash_tree(args: &Cli, params: &FileParams, dir: &str) -> Result<Digest, (String, HashError)> {
+    if !Path::new(dir).is_dir() {
+        return Err((dir.to_owned(), HashError::Open(io::Error::other("not a directory"))));
+    }
+
+    let mut files = Vec::new();
+    walk_dir(Path::new(dir), args, &mut files);
+
+    let mut size = 0u64;
+    let mut entries = Vec::with_capacity(files.len());
+    for file in files {
+        let digest = hash_file(args, params, &file).map_err(|error| (file.clone(), error))?;
+        size = size.saturating_add(digest.size);
+        //Walked paths are valid UTF-8
+        let relative = Path::new(&file).strip_prefix(dir).unwrap_or(Path::new(&file)).iter()
+                                                          .map(|part| part.to_string_lossy())
+                                                          .collect::<Vec<_>>()
+                                                          .join("/");
+        entries.push((relative, digest.hashes));
+    }
+
+    let hashes = args.kind.iter().enumerate().map(|(idx, kind)| {
+        let files = entries.iter().map(|(path, hashes)| (path.as_str(), hashes[idx])).collect::<Vec<_>>();
+        tree_hash(kind, &params.global, &files)
+    }).collect();
+    Ok(Digest {
+        hashes,
+        size,
+    })
+}
+
+///Loads secret from file, if `text` is path to existing file, otherwise decodes `text` as hex string.
+fn load_secret(text: &str) -> Result<Secret, String> {
+    let bytes = if Path::new(text).is_file() {
+        fs::read(text).map_err(|error| format!("{}: cannot read: {}", text, error))?
+    } else {
+        decode_hex(text).ok_or_else(|| format!("'{}' is neither existing file nor hex string", text))?
+    };
+
+    //Streaming hasher takes only secret of default size, while hashes with cut secret would differ from reference ones
+    match <[u8; SECRET_SIZE]>::try_from(bytes.as_slice()) {
+        Ok(secret) => Ok(Secret(Box::new(secret))),
+        Err(_) => Err(format!("secret must be exactly {} bytes long, but got {} bytes", SECRET_SIZE, bytes.len())),
+    }
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if text.is_empty() || !text.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let mut result = Vec::with_capacity(text.len() / 2);
+    for pair in text.chunks_exact(2) {
+        let high = (pair[0] as char).to_digit(16)?;
+        let low = (pair[1] as char).to_digit(16)?;
+        result.push((high << 4 | low) as u8);
+    }
+
+    Some(result)
+}
+
+///Parses seed as hex, if prefixed with `0x`, or as decimal otherwise.
+fn parse_seed(text: &str) -> Result<u64, String> {
+    let result = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+
+    result.map_err(|error| match error.kind() {
+        core::num::IntErrorKind::PosOverflow => format!("'{}' overflows 64bit seed", text),
+        _ => format!("'{}' is not valid seed", text),
+    })
+}
 
-                let hash = hasher.digest();
-                println!("{file}:{hash}");
-                hasher.reset(args.seed);
+///Hash parameters, with seeds of particular files overriding global one.
+struct FileParams {
+    global: Params,
+    seeds: HashMap<String, u64>,
+}
+
+impl FileParams {
+    fn seed(&self, file: &str) -> u64 {
+        self.seeds.get(file).copied().unwrap_or(self.global.seed)
+    }
+
+    fn get(&self, file: &str) -> Cow<'_, Params> {
+        match self.seeds.get(file) {
+            Some(&seed) => Cow::Owned(Params {
+                seed,
+                secret: self.global.secret.clone(),
+            }),
+            None => Cow::Borrowed(&self.global),
+        }
+    }
+}
+
+enum HashError {
+    Open(io::Error),
+    Read(io::Error),
+    SaveState(io::Error),
+}
+
+impl core::fmt::Display for HashError {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Open(error) if error.kind() == io::ErrorKind::IsADirectory => fmt.write_str("is a directory"),
+            Self::Open(error) => write!(fmt, "cannot open: {}", OpenReason(error)),
+            Self::Read(error) => write!(fmt, "error reading: {}", error),
+            Self::SaveState(error) => write!(fmt, "cannot save state: {}", error),
+        }
+    }
+}
+
+///Hashes file, passing every hashed chunk to `observe`.
+fn hash_file_with(args: &Cli, kinds: &[HashKind], params: &FileParams, file: &str, observe: &mut dyn FnMut(&[u8])) -> Result<Digest, HashError> {
+    //Number of bytes file should provide, according to its metadata
+    let expected_size = match args.detect_changes && !args.decompress && !args.follow && file != STDIN {
+        true => fs::metadata(file).ok().filter(|meta| meta.is_file()).map(|meta| {
+            let size = meta.len().saturating_sub(args.offset);
+            args.length.map_or(size, |length| size.min(length))
+        }),
+        false => None,
+    };
+    //Small files are read while opening, so it is timed too, to measure every file the same way
+    let start = Instant::now();
+    let mut reader = open_file(file, args).map_err(HashError::Open)?;
+    log::event(log::Level::Debug, "opened", &[("file", &file)]);
+    let mut size = 0u64;
+    let params = params.get(file);
+    let result = match &reader {
+        //Small files are already in memory, where one-shot functions avoid costly setup of streaming state
+        Input::Bytes { data, .. } if !args.include_names => {
+            size = data.len() as u64;
+            let hashes = kinds.iter().map(|kind| hash_oneshot(kind, &params, data)).collect();
+            if !data.is_empty() {
+                observe(data);
             }
+            Ok(hashes)
         },
-        HashKind::Xxh32 => {
-            let seed: u32 = match args.seed.try_into() {
-                Ok(seed) => seed,
-                Err(_) => {
-                    eprint!("{} is not valid seed for 32bit hash", args.seed);
-                    return;
-                }
-            };
-            let mut hasher = xxhash_rust::xxh32::Xxh32::new(seed);
-            for file in args.file.iter() {
-                let mut reader = match open_file(file) {
-                    Ok(file) => file,
-                    Err(error) => {
-                        eprintln!("{}: cannot open: {}", file, error);
-                        return;
-                    }
-                };
+        _ => hash_many_with(kinds, &params, |update| {
+            feed_name(args, file, update);
+            feed_input(&mut reader, &mut |chunk| {
+                update(chunk);
+                observe(chunk);
+            }).map(|fed| size = fed)
+        }),
+    };
 
-                loop {
-                    match reader.next() {
-                        Ok(None) => break,
-                        Ok(Some(chunk)) => hasher.update(chunk),
-                        Err(error) => {
-                            eprintln!("{}: error reading: {}", file, error);
-                            return;
-                        }
-                    }
-                }
+    if args.benchmark {
+        let elapsed = start.elapsed();
+        let throughput = match elapsed.as_secs_f64() {
+            secs if secs > 0.0 => size as f64 / (1024.0 * 1024.0) / secs,
+            _ => f64::INFINITY,
+        };
+        eprintln!("{}: {} bytes in {:.3}s, {:.2} MiB/s", file, size, elapsed.as_secs_f64(), throughput);
+    }
 
-                let hash = hasher.digest();
-                println!("{file}:{hash}");
-                hasher.reset(seed);
-            }
-        }
+    match expected_size {
+        Some(expected_size) if result.is_ok() && expected_size != size && log::is_enabled(log::Level::Warn) => {
+            eprintln!("{}: WARNING: file changed while hashing, expected {} bytes, but read {}", file, expected_size, size);
+        },
+        _ => (),
+    }
+
+    match result {
+        Ok(hashes) => {
+            log::event(log::Level::Debug, "hashed", &[("file", &file), ("size", &size)]);
+            Ok(Digest {
+                hashes,
+                size,
+            })
+        },
+        Err(error) => Err(HashError::Read(error)),
+    }
+}
+
+fn hash_file(args: &Cli, params: &FileParams, file: &str) -> Result<Digest, HashError> {
+    hash_file_with(args, &args.kind, params, file, &mut |_| ())
+}
+
+///Loads state to resume from `--resume-state` or creates initial one.
+fn load_state(args: &Cli, seed: u64) -> Result<State, String> {
+    let kind = args.kind[0];
+    if !State::is_supported(kind) {
+        return Err(format!("--save-state and --resume-state support only xxh32 and xxh64, but {} is specified", kind.name()));
+    }
+
+    let path = match args.resume_state.as_deref() {
+        Some(path) => path,
+        None => return Ok(State::new(kind, seed).expect("supported algorithm")),
+    };
+
+    let text = fs::read_to_string(path).map_err(|error| format!("{}: cannot read: {}", path, error))?;
+    let state = text.parse::<State>().map_err(|error| format!("{}: invalid state: {}", path, error))?;
+    if state.kind() != kind {
+        Err(format!("{}: state is saved for {}, but {} is specified", path, state.kind().name(), kind.name()))
+    } else if args.seed.is_some() && state.seed() != seed {
+        Err(format!("{}: state is saved with seed {}, but {} is specified", path, state.seed(), seed))
+    } else {
+        Ok(state)
+    }
+}
+
+///Writes state via temporary file, so that interruption does not leave it incomplete.
+fn save_state(path: &str, state: &State) -> io::Result<()> {
+    let temp = format!("{}.tmp", path);
+    fs::write(&temp, state.to_string()).and_then(|_| fs::rename(&temp, path))
+                                       .map_err(|error| io::Error::new(error.kind(), format!("{}: {}", path, error)))
+}
+
+///Hashes single input continuing from `state`, which is saved to `--save-state` periodically and once input ends.
+fn hash_resumable(args: &Cli, file: &str, state: &mut State) -> Result<Digest, HashError> {
+    let mut input = open_file(file, args).map_err(HashError::Open)?;
+    let path = args.save_state.as_deref();
+    let mut unsaved = 0u64;
+    let mut save_result = Ok(());
+    let result = feed_input(&mut input, &mut |chunk| {
+        state.update(chunk);
+        unsaved = unsaved.saturating_add(chunk.len() as u64);
+        if let Some(path) = path {
+            if unsaved >= STATE_SAVE_INTERVAL && save_result.is_ok() {
+                unsaved = 0;
+                save_result = save_state(path, state);
+            }
+        }
+    });
+
+    //State covers all data hashed before error, hence it is saved regardless
+    if let Some(path) = path {
+        save_result = save_result.and_then(|_| save_state(path, state));
+    }
+
+    result.map_err(HashError::Read)?;
+    save_result.map_err(HashError::SaveState)?;
+    Ok(Digest {
+        hashes: vec![state.finish()],
+        size: state.len(),
+    })
+}
+
+///Progress of hashing single file, reported to stderr.
+struct Progress<'a> {
+    file: &'a str,
+    total: u64,
+    done: u64,
+    last_report: Instant,
+    is_reported: bool,
+}
+
+impl<'a> Progress<'a> {
+    ///Minimal interval between reports.
+    const INTERVAL: Duration = Duration::from_millis(200);
+
+    fn new(file: &'a str, total: u64) -> Self {
+        Self {
+            file,
+            total,
+            done: 0,
+            last_report: Instant::now(),
+            is_reported: false,
+        }
+    }
+
+    fn percent(&self) -> u64 {
+        match self.total {
+            0 => 100,
+            total => self.done.min(total).saturating_mul(100) / total,
+        }
+    }
+
+    fn advance(&mut self, size: usize) {
+        self.done = self.done.saturating_add(size as u64);
+        if self.last_report.elapsed() >= Self::INTERVAL {
+            self.last_report = Instant::now();
+            self.is_reported = true;
+            eprint!("\r{}: {}%", self.file, self.percent());
+        }
+    }
+
+    fn finish(self) {
+        if self.is_reported {
+            eprintln!("\r{}: {}%", self.file, self.percent());
+        }
+    }
+}
+
+///Hashes file, reporting progress if its size is known.
+fn hash_file_with_progress(args: &Cli, params: &FileParams, file: &str) -> Result<Digest, HashError> {
+    let size = match fs::metadata(file) {
+        Ok(meta) if file != STDIN && meta.is_file() => meta.len(),
+        Ok(meta) if file != STDIN && args.device && is_block_device(&meta) => match device_size(file) {
+            Ok(size) => size,
+            Err(_) => return hash_file(args, params, file),
+        },
+        _ => return hash_file(args, params, file),
+    };
+    let size = size.saturating_sub(args.offset);
+    let total = match args.length {
+        Some(length) => size.min(length),
+        None => size,
+    };
+
+    let mut progress = Progress::new(file, total);
+    let result = hash_file_with(args, &args.kind, params, file, &mut |chunk| progress.advance(chunk.len()));
+    progress.finish();
+    result
+}
+
+///Hashes `files` using up to `jobs` threads.
+///
+///Passes each result with index of its file to `on_result` as soon as it is ready.
+///
+///With `--fail-fast`, stops after first failed file.
+fn hash_files_parallel(args: &Cli, params: &FileParams, files: &[String], jobs: usize, mut on_result: impl FnMut(usize, Result<Digest, HashError>)) {
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(files.len()) {
+            let next = &next;
+            let sender = sender.clone();
+            scope.spawn(move || loop {
+                if interrupt::is_interrupted() {
+                    break;
+                }
+                let idx = next.fetch_add(1, Ordering::Relaxed);
+                match files.get(idx) {
+                    Some(file) => if sender.send((idx, hash_file(args, params, file))).is_err() {
+                        break;
+                    },
+                    None => break,
+                }
+            });
+        }
+        drop(sender);
+
+        for (idx, result) in receiver {
+            let is_failed = result.is_err();
+            on_result(idx, result);
+            //Dropping receiver makes workers stop once their current file is done
+            if is_failed && args.fail_fast {
+                break;
+            }
+        }
+    })
+}
+
+///Hashes every file, collecting results along with index of file in order of completion.
+fn collect_results(args: &Cli, params: &FileParams, files: &[String]) -> Vec<(usize, Result<Digest, HashError>)> {
+    let mut results = Vec::with_capacity(files.len());
+    match args.jobs > 1 && files.len() > 1 {
+        true => hash_files_parallel(args, params, files, args.jobs, |idx, result| results.push((idx, result))),
+        false => for (idx, file) in files.iter().enumerate() {
+            if interrupt::is_interrupted() {
+                break;
+            }
+            let result = hash_file(args, params, file);
+            let is_failed = result.is_err();
+            results.push((idx, result));
+            if is_failed && args.fail_fast {
+                break;
+            }
+        },
+    }
+    results
+}
+
+///Sorts results of `files`, keeping order of arguments among equal ones.
+fn sort_results(sort: Sort, files: &[String], results: &mut [(usize, Result<Digest, HashError>)]) {
+    match sort {
+        Sort::None => results.sort_by_key(|(idx, _)| *idx),
+        Sort::Name => results.sort_by(|(left, _), (right, _)| files[*left].cmp(&files[*right]).then(left.cmp(right))),
+        Sort::Hash => results.sort_by_key(|(idx, result)| {
+            //Failed files go last
+            let hash = result.as_ref().ok().map(|digest| digest.hashes[0].as_u128());
+            (hash.is_none(), hash, &files[*idx], *idx)
+        }),
+    }
+}
+
+///Returns formatting of hashes requested by output options.
+fn hash_format(args: &Cli) -> HashFormat {
+    let encoding = match () {
+        _ if args.uuid => Encoding::Uuid,
+        _ if args.base64 => Encoding::Base64,
+        _ if args.base64url => Encoding::Base64Url,
+        _ if args.hex || args.canonical => Encoding::Hex,
+        _ => Encoding::Decimal,
+    };
+    HashFormat {
+        encoding,
+        uppercase: args.uppercase,
+        width: args.width,
+    }
+}
+
+///Makes `path` absolute and removes `.` and `..` components, without resolving symbolic links.
+fn normalize_path(path: &Path) -> io::Result<PathBuf> {
+    let path = match path.is_absolute() {
+        true => path.to_owned(),
+        false => std::env::current_dir()?.join(path),
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                normalized.pop();
+            },
+            component => normalized.push(component),
+        }
+    }
+    Ok(normalized)
+}
+
+///Returns path of `file` relative to normalized `base`, or its absolute path if it is outside.
+fn relative_path(base: &Path, file: &str) -> Result<String, String> {
+    let path = match normalize_path(Path::new(file)) {
+        Ok(path) => path,
+        Err(_) => return Err(file.to_owned()),
+    };
+    match path.strip_prefix(base) {
+        Ok(relative) => Ok(relative.to_string_lossy().into_owned()),
+        Err(_) => Err(path.to_string_lossy().into_owned()),
+    }
+}
+
+///Returns name of `file` to show in output, which is `--name` for stdin.
+///
+///With `--relative-to` it is relative to base, or absolute path if it is outside.
+fn display_name<'a>(args: &'a Cli, file: &'a str) -> Cow<'a, str> {
+    match (file, args.name.as_deref(), args.relative_to.as_deref()) {
+        (STDIN, Some(name), _) => name.into(),
+        (STDIN, None, _) | (_, _, None) => file.into(),
+        (_, _, Some(base)) => match relative_path(Path::new(base), file) {
+            Ok(relative) | Err(relative) => relative.into(),
+        },
+    }
+}
+
+///Prints results of hashing to output
+struct Printer<'a> {
+    args: &'a Cli,
+    params: &'a FileParams,
+    out: Box<dyn Write + 'a>,
+    ///Name of output in error messages
+    out_name: &'a str,
+    ///Whether writing to output failed
+    is_write_failed: bool,
+    line: LineFormat,
+    hash_format: HashFormat,
+    count: usize,
+    ///Number of hashed inputs
+    hashed_count: usize,
+    ///Total number of hashed bytes
+    hashed_size: u64,
+    ///Number of inputs that failed
+    failed_count: usize,
+    ///Number of inputs that failed to open
+    open_failed_count: usize,
+    ///Whether no error has been reported
+    is_ok: bool,
+}
+
+impl<'a> Printer<'a> {
+    fn new(args: &'a Cli, params: &'a FileParams, out: Box<dyn Write + 'a>, out_name: &'a str) -> Self {
+        Self {
+            args,
+            params,
+            out,
+            out_name,
+            is_write_failed: false,
+            line: LineFormat {
+                format: args.format,
+                separator: args.separator.replace("\\t", "\t"),
+                terminator: line_terminator(args),
+                label: args.label.clone(),
+                no_names: args.no_names,
+                is_multiple: args.kind.len() > 1,
+            },
+            hash_format: hash_format(args),
+            count: 0,
+            hashed_count: 0,
+            hashed_size: 0,
+            failed_count: 0,
+            open_failed_count: 0,
+            is_ok: true,
+        }
+    }
+
+    #[inline(always)]
+    fn is_json(&self) -> bool {
+        //Raw output takes priority
+        (self.args.json || self.args.ndjson) && !self.args.raw
+    }
+
+    #[inline(always)]
+    fn is_ndjson(&self) -> bool {
+        self.args.ndjson && !self.args.raw
+    }
+
+    ///Reports first failure to write output.
+    fn check_write(&mut self, result: io::Result<()>) {
+        if let Err(error) = result {
+            self.is_ok = false;
+            if !self.is_write_failed {
+                self.is_write_failed = true;
+                eprintln!("{}: cannot write, output is incomplete: {}", self.out_name, error);
+            }
+        }
+    }
+
+    fn begin(&mut self) {
+        if self.is_json() && !self.is_ndjson() {
+            let result = self.out.write_all(b"[");
+            self.check_write(result);
+        }
+    }
+
+    fn json_entry(&mut self, entry: &str) {
+        let line_end = self.args.line_ending.as_str();
+        let result = if self.is_ndjson() {
+            write!(self.out, "{}{}", entry, line_end)
+        } else if self.count > 0 {
+            write!(self.out, ",{}{}", line_end, entry)
+        } else {
+            write!(self.out, "{}{}", line_end, entry)
+        };
+        self.check_write(result);
+        self.count += 1;
+    }
+
+    fn hash(&mut self, file: &str, kind: &HashKind, hash: HashResult, size: u64) {
+        if self.args.quiet {
+            return;
+        }
+
+        if self.args.raw {
+            let result = match hash {
+                HashResult::U32(hash) => self.out.write_all(&hash.to_be_bytes()),
+                HashResult::U64(hash) => self.out.write_all(&hash.to_be_bytes()),
+                //Same byte order as used to construct UUID
+                HashResult::U128(hash) => self.out.write_all(&hash.to_le_bytes()),
+            };
+            let result = result.and_then(|_| self.out.flush());
+            self.check_write(result);
+        } else if self.args.batch {
+            let mtime = match file {
+                STDIN => None,
+                file => fs::metadata(file).and_then(|meta| meta.modified()).ok()
+                                          .and_then(|mtime| mtime.duration_since(std::time::UNIX_EPOCH).ok()),
+            };
+            let mtime = mtime.map_or_else(|| "-".to_owned(), |mtime| mtime.as_secs().to_string());
+            let result = write!(self.out, "{}\t{}\t{}\t{}\0", size, mtime, self.hash_format.format(hash), display_name(self.args, file));
+            self.check_write(result);
+        } else if self.is_json() {
+            //Hash is always hex string, unless UUID is requested.
+            let hash = match hash {
+                HashResult::U128(_) if self.args.uuid => self.hash_format.format(hash),
+                hash => self.hash_format.hex(hash),
+            };
+            let mut entry = String::from("{\"file\":");
+            write_json_str(&mut entry, &display_name(self.args, file));
+            entry.push_str(",\"algorithm\":");
+            write_json_str(&mut entry, kind.name());
+            entry.push_str(&format!(",\"seed\":{},\"secret\":", self.params.seed(file)));
+            match self.secret_fingerprint(kind) {
+                Some(fingerprint) => write_json_str(&mut entry, &fingerprint),
+                None => entry.push_str("null"),
+            }
+            entry.push_str(",\"hash\":");
+            write_json_str(&mut entry, &hash);
+            if self.args.show_size {
+                entry.push_str(&format!(",\"size\":{}", size));
+            }
+            entry.push('}');
+            self.json_entry(&entry);
+        } else {
+            let mut hash = self.hash_format.format(hash);
+            if self.args.show_size {
+                let separator = match self.args.format {
+                    Format::Default => self.line.separator.as_str(),
+                    _ => " ",
+                };
+                hash.push_str(&format!("{}{}", separator, size));
+            }
+            if self.args.verbose {
+                hash.push_str(&format!(" ({}, seed {}", kind.name(), self.params.seed(file)));
+                if let Some(fingerprint) = self.secret_fingerprint(kind) {
+                    hash.push_str(&format!(", secret {}", fingerprint));
+                }
+                hash.push(')');
+            }
+            let result = self.line.write(&mut self.out, &display_name(self.args, file), kind, &hash);
+            self.check_write(result);
+        }
+    }
+
+    ///Returns fingerprint of custom secret, if it is used by `kind`.
+    fn secret_fingerprint(&self, kind: &HashKind) -> Option<String> {
+        match self.params.global.secret.as_ref() {
+            Some(secret) if kind.uses_secret() => Some(format!("{:016x}", secret.fingerprint())),
+            _ => None,
+        }
+    }
+
+    fn error(&mut self, file: &str, error: &HashError) {
+        self.is_ok = false;
+        self.failed_count += 1;
+        if let HashError::Open(_) = error {
+            self.open_failed_count += 1;
+        }
+        let file = display_name(self.args, file);
+        if self.is_json() {
+            let mut entry = String::from("{\"file\":");
+            write_json_str(&mut entry, &file);
+            entry.push_str(",\"error\":");
+            write_json_str(&mut entry, &error.to_string());
+            entry.push('}');
+            self.json_entry(&entry);
+        } else {
+            eprintln!("{}: {}", file, error);
+        }
+    }
+
+    ///Prints hashes in order of requested algorithms.
+    fn digest(&mut self, file: &str, digest: Digest) {
+        self.hashed_count += 1;
+        self.hashed_size = self.hashed_size.saturating_add(digest.size);
+        let args = self.args;
+        for (kind, hash) in args.kind.iter().zip(digest.hashes) {
+            self.hash(file, kind, hash, digest.size);
+        }
+    }
+
+    fn result(&mut self, file: &str, result: Result<Digest, HashError>) {
+        match result {
+            Ok(digest) => self.digest(file, digest),
+            Err(error) => self.error(file, &error),
+        }
+    }
+
+    ///Counts `count` inputs rejected before hashing, such as directories or patterns that matched no files, as inputs that failed to open.
+    fn rejected(&mut self, count: usize) {
+        if count > 0 {
+            self.is_ok = false;
+            self.failed_count += count;
+            self.open_failed_count += count;
+        }
+    }
+
+    ///Separates groups of duplicates by empty line.
+    fn group_separator(&mut self) {
+        let result = self.out.write_all(self.line.terminator.as_bytes());
+        self.check_write(result);
+    }
+
+    ///Reports counts of inputs out of `total`.
+    fn error_summary(&mut self, total: usize) {
+        let opened = (self.hashed_count + self.failed_count).saturating_sub(self.open_failed_count);
+        //Inputs are left unprocessed when hashing stops early
+        let skipped = total.saturating_sub(self.hashed_count + self.failed_count);
+        let summary = format!("{{\"summary\":{{\"opened\":{},\"hashed\":{},\"failed\":{},\"skipped\":{}}}}}", opened, self.hashed_count, self.failed_count, skipped);
+        match self.is_json() {
+            true => self.json_entry(&summary),
+            false => eprintln!("{}", summary),
+        }
+    }
+
+    fn finish(&mut self) {
+        let mut result = Ok(());
+        if self.is_json() && !self.is_ndjson() {
+            let line_end = self.args.line_ending.as_str();
+            result = write!(self.out, "{line_end}]{line_end}");
+        }
+        let result = result.and_then(|_| self.out.flush());
+        self.check_write(result);
+    }
+}
+
+///Returns ending of each line of hashes output.
+fn line_terminator(args: &Cli) -> &'static str {
+    match args.zero {
+        true => "\0",
+        false => args.line_ending.as_str(),
+    }
+}
+
+///Prints failed verification to stdout, or to stderr with `--quiet`, that leaves stdout empty.
+fn report_failure(args: &Cli, line: &str) {
+    match args.quiet {
+        true => eprint!("{}", line),
+        false => print!("{}", line),
+    }
+}
+
+///Compares hash of the single input against `expected`.
+///
+///Returns whether hash matched.
+fn expect(args: &Cli, params: &FileParams, files: &[String], expected: &str) -> bool {
+    let (name, result) = match (files, args.string.as_slice()) {
+        ([file], []) => (display_name(args, file).into_owned(), hash_file(args, params, file)),
+        ([], [text]) => (format!("\"{}\"", text), hash_string(&args.kind, &params.global, text).map_err(HashError::Read)),
+        _ => {
+            eprintln!("--expect requires exactly one input");
+            return false;
+        }
+    };
+
+    //Single algorithm is ensured on start
+    match result {
+        Ok(digest) => if check::is_expected(&hash_format(args), digest.hashes[0], expected) {
+            if !args.quiet {
+                print!("{name}: {}{}", args.color.paint(Color::GREEN, "OK"), args.line_ending.as_str());
+            }
+            true
+        } else {
+            report_failure(args, &format!("{name}: {}{}", args.color.paint(Color::RED, "MISMATCH"), args.line_ending.as_str()));
+            false
+        },
+        Err(error) => {
+            eprintln!("{}: {}", name, error);
+            false
+        }
+    }
+}
+
+///Reads expected hash for `--expect -` from stdin, extracting it from line of output if needed.
+fn read_expected() -> Result<String, String> {
+    let mut text = String::new();
+    io::stdin().lock().read_to_string(&mut text).map_err(|error| format!("{}: error reading expected hash: {}", STDIN, error))?;
+
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    match (lines.next(), lines.next()) {
+        (Some(line), None) => Ok(check::parse_line(line, InputFormat::Auto).map_or(line, |line| line.hash).to_owned()),
+        (None, _) => Err(format!("{}: expected hash is empty", STDIN)),
+        (Some(_), Some(_)) => Err(format!("{}: expected single hash, but multiple lines are provided", STDIN)),
+    }
+}
+
+///Compares hashes of exactly two files.
+///
+///Returns whether hashes are identical.
+fn compare(args: &Cli, params: &FileParams, files: &[String]) -> bool {
+    let (left, right) = match files {
+        [left, right] => (left, right),
+        _ => {
+            eprintln!("--compare requires exactly two files, but {} specified", files.len());
+            return false;
+        }
+    };
+
+    let mut digests = Vec::with_capacity(2);
+    for file in [left, right] {
+        match hash_file(args, params, file) {
+            Ok(digest) => digests.push(digest.hashes),
+            Err(error) => {
+                eprintln!("{}: {}", file, error);
+                return false;
+            }
+        }
+    }
+
+    let is_identical = digests[0] == digests[1];
+    if !args.quiet {
+        match is_identical {
+            true => print!("{left} {right}: {}{}", args.color.paint(Color::GREEN, "IDENTICAL"), args.line_ending.as_str()),
+            false => print!("{left} {right}: {}{}", args.color.paint(Color::RED, "DIFFERENT"), args.line_ending.as_str()),
+        }
+    }
+    is_identical
+}
+
+fn open_lines(path: &str) -> io::Result<Box<dyn BufRead>> {
+    if path == STDIN {
+        Ok(Box::new(io::stdin().lock()))
+    } else {
+        Ok(Box::new(io::BufReader::new(File::open(path)?)))
+    }
+}
+
+///Verifies checksums listed within `sums`, resolving relative paths against `base` if specified.
+fn check_sums(args: &Cli, params: &FileParams, sums: &str, base: Option<&Path>, summary: &mut check::Summary) {
+    let reader = match open_lines(sums) {
+        Ok(reader) => reader,
+        Err(error) => {
+            eprintln!("{}: cannot open: {}", sums, OpenReason(&error));
+            summary.errors += 1;
+            return;
+        }
+    };
+
+    //Checksum file, that has every listed file missing, verifies nothing
+    let verified = summary.ok + summary.failed + summary.missing;
+    let mut ignored = 0;
+    for (idx, line) in reader.lines().enumerate() {
+        if interrupt::is_interrupted() || (args.fail_fast && !summary.is_ok(args.strict)) {
+            break;
+        }
+
+        let line = match line {
+            Ok(line) => line,
+            Err(error) => {
+                eprintln!("{}: error reading: {}", sums, error);
+                summary.errors += 1;
+                break;
+            }
+        };
+        let line = line.trim_end_matches('\r');
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let line = match check::parse_line(line, args.input_format) {
+            Some(line) => line,
+            None => {
+                eprintln!("{}:{}: improperly formatted checksum line", sums, idx + 1);
+                summary.malformed += 1;
+                continue;
+            }
+        };
+        //Single algorithm is ensured on start, while bsd lines specify their own
+        let kind = match line.label {
+            None => args.kind[0],
+            Some(label) if args.label.as_deref() == Some(label) => args.kind[0],
+            Some(label) => match HashKind::from_label(label) {
+                //xxh3 and xxh128 share label
+                Some(kind) if kind.label() == args.kind[0].label() => args.kind[0],
+                Some(kind) => kind,
+                None => {
+                    eprintln!("{}:{}: unknown algorithm label '{}'", sums, idx + 1, label);
+                    summary.malformed += 1;
+                    continue;
+                }
+            },
+        };
+        let (expected, file) = (line.hash, line.file);
+
+        let file = match base {
+            Some(base) if !Path::new(file).is_absolute() => base.join(file).to_string_lossy().into_owned(),
+            _ => file.to_owned(),
+        };
+
+        let result = hash_file_with(args, &[kind], params, &file, &mut |_| ());
+        match &result {
+            Err(HashError::Open(error)) if args.ignore_missing && error.kind() == io::ErrorKind::NotFound => {
+                ignored += 1;
+                continue;
+            },
+            _ => (),
+        }
+
+        match result {
+            Ok(digest) => if check::is_expected(&hash_format(args), digest.hashes[0], expected) {
+                summary.ok += 1;
+                if !args.quiet && !args.only_failed {
+                    print!("{file}: {}{}", args.color.paint(Color::GREEN, "OK"), args.line_ending.as_str());
+                }
+            } else {
+                summary.failed += 1;
+                report_failure(args, &format!("{file}: {}{}", args.color.paint(Color::RED, "FAILED"), args.line_ending.as_str()));
+            },
+            Err(error) => {
+                let color = match &error {
+                    HashError::Open(error) if error.kind() == io::ErrorKind::NotFound => {
+                        summary.missing += 1;
+                        Color::YELLOW
+                    },
+                    _ => {
+                        summary.failed += 1;
+                        Color::RED
+                    }
+                };
+                eprintln!("{}: {}", file, error);
+                report_failure(args, &format!("{file}: {}{}", args.color.paint(color, "FAILED open or read"), args.line_ending.as_str()));
+            }
+        }
+    }
+
+    if ignored > 0 && summary.ok + summary.failed + summary.missing == verified {
+        eprintln!("{}: no file was verified", sums);
+        summary.errors += 1;
+    }
+}
+
+///Verifies checksums listed within each of `files`.
+///
+///Directories are searched for `.xxh` and `.xxh3` files, that list paths relative to their own location.
+///
+///Returns whether every checksum matched.
+fn check(args: &Cli, params: &FileParams, files: &[String]) -> bool {
+    let mut summary = check::Summary::default();
+
+    for sums in files.iter() {
+        if args.fail_fast && !summary.is_ok(args.strict) {
+            break;
+        }
+        if sums != STDIN && Path::new(sums).is_dir() {
+            let mut sidecars = Vec::new();
+            walk_dir(Path::new(sums), args, &mut sidecars);
+            for sidecar in sidecars.iter().filter(|file| check::is_sidecar(file)) {
+                check_sums(args, params, sidecar, Path::new(sidecar).parent(), &mut summary);
+            }
+        } else {
+            check_sums(args, params, sums, None, &mut summary);
+        }
+    }
+
+    if !args.quiet {
+        eprintln!("{} OK, {} FAILED, {} missing", summary.ok, summary.failed, summary.missing);
+    }
+
+    summary.is_ok(args.strict)
+}
+
+fn print_version() {
+    println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    println!("xxhash-rust {} (features: {})", env!("XXHASH_RUST_VERSION"), env!("XXHASH_RUST_FEATURES"));
+    let algorithms = HashKind::ALL.iter().map(HashKind::name).collect::<Vec<_>>();
+    println!("algorithms: {}", algorithms.join(", "));
+}
+
+///Environment variable overriding default seed.
+const SEED_ENV: &str = "XXHASH_SEED";
+
+///Environment variable overriding default hash algorithm.
+const DEFAULT_KIND_ENV: &str = "XXHASH_DEFAULT";
+
+///Environment variable setting default encoding of hashes.
+const OUTPUT_ENV: &str = "XXHASH_OUTPUT";
+
+///Applies encoding of hashes from environment, unless one is specified on command line.
+fn apply_default_output(args: &mut Cli) -> Result<(), String> {
+    let output = match std::env::var(OUTPUT_ENV) {
+        Ok(output) if !output.is_empty() => output,
+        _ => return Ok(()),
+    };
+
+    if output.eq_ignore_ascii_case("hex") {
+        args.hex |= !(args.decimal || args.canonical || args.base64 || args.base64url || args.uuid || args.raw);
+    } else if output.eq_ignore_ascii_case("base64") {
+        args.base64 |= !(args.decimal || args.hex || args.canonical || args.base64url || args.uuid || args.raw);
+    } else if !output.eq_ignore_ascii_case("dec") {
+        return Err(format!("{}: unknown encoding '{}', valid options: hex, dec, base64", OUTPUT_ENV, output));
+    }
+    Ok(())
+}
+
+///Returns algorithm used when none is specified on command line or by configuration file.
+fn default_kind(config: Option<HashKind>) -> HashKind {
+    match std::env::var(DEFAULT_KIND_ENV) {
+        Ok(kind) => match kind.parse() {
+            Ok(kind) => kind,
+            Err(_) => {
+                eprintln!("{}: {}", DEFAULT_KIND_ENV, unknown_kind(&kind));
+                std::process::exit(1);
+            }
+        },
+        Err(_) => config.unwrap_or(HashKind::Xxh3),
+    }
+}
+
+///Describes first unknown algorithm within comma separated list `text`.
+fn unknown_kind(text: &str) -> String {
+    let unknown = text.split(',').find(|kind| kind.parse::<HashKind>().is_err()).unwrap_or(text);
+    let valid = HashKind::ALL.iter().map(HashKind::name).collect::<Vec<_>>();
+    format!("unknown algorithm '{}', valid options: {}", unknown, valid.join(", "))
+}
+
+///Returns whether `text`, that is not algorithm, is rather misspelled algorithm than input.
+///
+///Names of missing files without extension or directory, e.g. `md5`, are taken as algorithms.
+fn is_misspelled_kind(text: &str) -> bool {
+    let is_kind_like = text.get(..3).map_or(false, |prefix| prefix.eq_ignore_ascii_case("xxh")) || text.contains(',');
+    let is_name_like = text.starts_with(|ch: char| ch.is_ascii_alphabetic()) && text.chars().all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-'));
+    (is_kind_like || is_name_like) && !Path::new(text).exists()
+}
+
+fn parse_error(error: arg::ParseError<'_>) -> ! {
+    match error {
+        arg::ParseError::HelpRequested(help) => {
+            println!("{}", help);
+            std::process::exit(0);
+        },
+        error => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+///Hashes test vectors both by streaming hasher, in chunks not aligned to its blocks, and at once.
+///
+///Returns whether every hash matched.
+fn self_test(args: &Cli) -> bool {
+    let mut failed = 0;
+
+    for (kind, input, seed, is_secret, expected) in vectors::VECTORS.iter() {
+        let data = vectors::input(*input);
+        let params = vectors::params(*seed, *is_secret);
+        let streaming = hash_with(kind, &params, |update| {
+            data.chunks(7).for_each(|chunk| update(chunk));
+            Ok::<_, ()>(())
+        });
+        let is_ok = streaming.map_or(false, |hash| hash.as_u128() == *expected) && hash_oneshot(kind, &params, &data).as_u128() == *expected;
+
+        let status = match is_ok {
+            true => args.color.paint(Color::GREEN, "PASS"),
+            false => {
+                failed += 1;
+                args.color.paint(Color::RED, "FAIL")
+            }
+        };
+        let secret = match is_secret {
+            true => " with secret",
+            false => "",
+        };
+        print!("{} {} seed {}{}: {}{}", kind.name(), vectors::INPUTS[*input], seed, secret, status, args.line_ending.as_str());
+    }
+
+    eprintln!("{} PASS, {} FAIL", vectors::VECTORS.len() - failed, failed);
+    failed == 0
+}
+
+///Handles requests that terminate program right after parsing.
+fn handle_version(cli: Cli) -> Cli {
+    if cli.version {
+        print_version();
+        std::process::exit(0);
+    } else if cli.self_test {
+        std::process::exit(match self_test(&cli) {
+            true => 0,
+            false => 1,
+        });
+    } else if cli.list_algorithms {
+        for kind in HashKind::ALL.iter() {
+            println!("{}\t{}", kind.name(), kind.bits());
+        }
+        std::process::exit(0);
+    }
+    cli
+}
+
+///Applies `settings`, unless overridden by `cli_args`.
+///
+///Setting, that conflicts with options of command line, is ignored too, as explicit options take priority.
+fn apply_settings(args: &mut Cli, settings: Vec<Setting>, cli_args: &[String]) {
+    for setting in settings {
+        if setting.options().iter().any(|option| cli_args.iter().any(|arg| arg == option)) {
+            continue;
+        }
+
+        let mut candidate = args.clone();
+        setting.apply(&mut candidate);
+        match validate(&candidate) {
+            Err(error) if validate(args).err().as_ref() != Some(&error) => log::event(log::Level::Debug, "setting ignored", &[("reason", &error)]),
+            _ => *args = candidate,
+        }
+    }
+}
+
+///Parses command line, exiting on help, version or error.
+fn parse_args() -> Cli {
+    let config = match config::load() {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    let mut args = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.strip_prefix("--preset=") {
+            Some(preset) => args.extend(["--preset".to_owned(), preset.to_owned()]),
+            None => args.push(arg),
+        }
+    }
+    let finish = |mut cli: Cli| {
+        //Presets are part of command line, so they take priority over configuration file
+        let mut settings = config.settings;
+        settings.extend(cli.preset.iter().flat_map(Preset::settings));
+        apply_settings(&mut cli, settings, &args);
+        handle_version(cli)
+    };
+
+    //Algorithm is optional, so when first positional argument is not an algorithm, it is input.
+    let kind_idx = match Cli::from_args(args.iter().map(String::as_str)) {
+        Ok(cli) => return finish(cli),
+        Err(arg::ParseError::RequiredArgMissing("kind")) => args.len(),
+        Err(arg::ParseError::InvalidArgValue("kind", value)) if is_misspelled_kind(value) => {
+            eprintln!("{}", unknown_kind(value));
+            std::process::exit(1);
+        },
+        Err(arg::ParseError::InvalidArgValue("kind", value)) => match args.iter().position(|arg| core::ptr::eq(arg.as_str(), value)) {
+            Some(idx) => idx,
+            None => parse_error(arg::ParseError::InvalidArgValue("kind", value)),
+        },
+        Err(error) => parse_error(error),
+    };
+
+    let mut with_kind = args.clone();
+    with_kind.insert(kind_idx, default_kind(config.algorithm).name().to_owned());
+    match Cli::from_args(with_kind.iter().map(String::as_str)) {
+        Ok(cli) => finish(cli),
+        Err(error) => parse_error(error),
+    }
+}
+
+///Reads seed from first line of file, keeping it out of command line.
+fn read_seed(path: &str) -> Result<u64, String> {
+    let text = fs::read_to_string(path).map_err(|error| format!("{}: cannot read seed: {}", path, OpenReason(&error)))?;
+    match text.lines().next().map(str::trim) {
+        Some(seed) if !seed.is_empty() => parse_seed(seed).map_err(|error| format!("{}: {}", path, error)),
+        _ => Err(format!("{}: seed file is empty", path)),
+    }
+}
+
+///Parses seed from `--seed` or environment, validating it fits every requested algorithm.
+fn resolve_seed(args: &Cli) -> Result<u64, String> {
+    let seed = match args.seed.as_deref() {
+        Some(seed) => match seed.strip_prefix('@') {
+            Some(path) => read_seed(path)?,
+            None => parse_seed(seed)?,
+        },
+        None => match std::env::var(SEED_ENV) {
+            Ok(seed) => parse_seed(&seed).map_err(|error| format!("{}: {}", SEED_ENV, error))?,
+            Err(_) => 0,
+        },
+    };
+
+    if args.kind.contains(&HashKind::Xxh32) && u32::try_from(seed).is_err() {
+        return Err(format!("{} is not valid seed for 32bit hash", seed));
+    }
+
+    Ok(seed)
+}
+
+///Returns description of first conflict among options, if any.
+fn validate(args: &Cli) -> Result<(), String> {
+    //Tag is applied once options are valid
+    let format = match args.tag {
+        true => &Format::Bsd,
+        false => &args.format,
+    };
+    let (is_default, is_coreutils, is_bsd) = (matches!(format, Format::Default), matches!(format, Format::Coreutils), matches!(format, Format::Bsd));
+    let is_state = args.save_state.is_some() || args.resume_state.is_some();
+    let is_json = args.json || args.ndjson;
+    let is_multiple = args.kind.len() > 1;
+    let is_sorted = args.sort != Sort::None;
+    let is_records = args.records.is_some();
+
+    let conflicts = [
+        (is_sorted && args.ndjson, "--sort cannot be combined with --ndjson, which prints results as soon as they are ready"),
+        (args.tree_hash && args.combined, "--tree-hash cannot be combined with --combined"),
+        (args.total && args.combined, "--total cannot be combined with --combined"),
+        (args.total && args.tree_hash, "--total cannot be combined with --tree-hash"),
+        (args.total && is_sorted, "--total cannot be combined with --sort"),
+        (is_records && (!args.string.is_empty() || args.files_from.is_some() || args.from_null || args.file.iter().any(|file| file != STDIN)), "--records cannot be combined with inputs other than stdin"),
+        (is_records && args.check, "--records cannot be combined with --check"),
+        (is_records && args.expect.is_some(), "--records cannot be combined with --expect"),
+        (is_records && args.compare, "--records cannot be combined with --compare"),
+        (is_records && args.combined, "--records cannot be combined with --combined"),
+        (is_records && args.tree_hash, "--records cannot be combined with --tree-hash"),
+        (is_records && args.total, "--records cannot be combined with --total"),
+        (is_records && is_sorted, "--records cannot be combined with --sort"),
+        (is_records && args.include_names, "--records cannot be combined with --include-names"),
+        (is_records && is_state, "--records cannot be combined with --save-state"),
+        //Saved state counts only content bytes to resume from
+        (args.include_names && is_state, "--include-names cannot be combined with --save-state or --resume-state"),
+        (args.head.is_some() && args.length.is_some(), "--head cannot be combined with --length"),
+        (args.buffer_size == 0, "Buffer size must be greater than 0"),
+        (args.tag && matches!(args.format, Format::Coreutils), "--tag cannot be combined with --format coreutils"),
+        (is_multiple && args.check, "--check supports only single algorithm"),
+        (is_multiple && args.expect.is_some(), "--expect supports only single algorithm"),
+        (is_multiple && is_coreutils, "--format coreutils supports only single algorithm"),
+        (is_multiple && is_state, "--save-state supports only single algorithm"),
+        (is_multiple && args.label.is_some(), "--label supports only single algorithm"),
+        (is_multiple && args.batch, "--batch supports only single algorithm"),
+        (args.label.is_some() && !is_bsd && !args.check, "--label requires --format bsd or --tag"),
+        (args.follow && args.mmap, "--follow cannot be combined with --mmap"),
+        (args.follow && args.sparse, "--follow cannot be combined with --sparse"),
+        (args.follow && args.decompress, "--follow cannot be combined with --decompress"),
+        (args.sparse && args.mmap, "--sparse cannot be combined with --mmap"),
+        (args.append && args.output.is_none(), "--append requires --output"),
+        (args.no_names && args.json, "--no-names cannot be combined with --json"),
+        (args.no_names && args.ndjson, "--no-names cannot be combined with --ndjson"),
+        (args.no_names && is_coreutils, "--no-names cannot be combined with --format coreutils"),
+        (args.no_names && is_bsd, "--no-names cannot be combined with --format bsd"),
+        (args.find_duplicates && is_json, "--find-duplicates cannot be combined with --json or --ndjson"),
+        (args.find_duplicates && args.raw, "--find-duplicates cannot be combined with --raw"),
+        (args.find_duplicates && args.batch, "--find-duplicates cannot be combined with --batch"),
+        (args.find_duplicates && args.combined, "--find-duplicates cannot be combined with --combined"),
+        (args.find_duplicates && args.tree_hash, "--find-duplicates cannot be combined with --tree-hash"),
+        (args.find_duplicates && args.total, "--find-duplicates cannot be combined with --total"),
+        (args.find_duplicates && is_sorted, "--find-duplicates cannot be combined with --sort"),
+        (args.find_duplicates && is_records, "--find-duplicates cannot be combined with --records"),
+        (args.find_duplicates && is_state, "--find-duplicates cannot be combined with --save-state"),
+        (args.batch && is_json, "--batch cannot be combined with --json or --ndjson"),
+        (args.batch && args.raw, "--batch cannot be combined with --raw"),
+        (args.batch && args.no_names, "--batch cannot be combined with --no-names"),
+        (args.batch && args.show_size, "--batch cannot be combined with --show-size"),
+        (args.batch && args.verbose, "--batch cannot be combined with --verbose"),
+        (args.batch && is_coreutils, "--batch cannot be combined with --format coreutils"),
+        (args.batch && is_bsd, "--batch cannot be combined with --format bsd"),
+        (args.verbose && !is_default, "--verbose requires default format, as checksum files with extra details cannot be verified"),
+        (args.width.is_some() && (!(args.hex || args.canonical || is_json) || args.base64 || args.base64url || args.uuid), "--width requires hex output: --hex, --canonical or --json"),
+    ];
+    if let Some((_, conflict)) = conflicts.iter().find(|(is_conflict, _)| *is_conflict) {
+        return Err(conflict.to_string());
+    }
+
+    if let Some(label) = args.label.as_deref() {
+        //Label must not prevent parsing of checksum line
+        if label.is_empty() || label.contains(|ch: char| ch.is_whitespace() || ch.is_control() || matches!(ch, '(' | ')' | '=')) {
+            return Err(format!("--label '{}' must be non-empty and cannot contain whitespace, parentheses or =", label));
+        }
+    }
+    if let Some(width) = args.width {
+        if let Some(kind) = args.kind.iter().find(|kind| kind.bits() as usize / 4 > width) {
+            return Err(format!("--width {} is less than {} hex digits of {}", width, kind.bits() / 4, kind.name()));
+        }
+    }
+    if args.uuid {
+        if let Some(kind) = args.kind.iter().find(|kind| kind.bits() != 128) {
+            return Err(format!("--uuid requires 128bit algorithm (xxh3 or xxh128), but {} is {}bit", kind.name(), kind.bits()));
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let start = Instant::now();
+    let mut args = parse_args();
+    log::set_level(args.log_level);
+
+    if let Err(error) = apply_default_output(&mut args) {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+
+    let seed = match resolve_seed(&args) {
+        Ok(seed) => seed,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+
+    //Stdin goes after every other source, so that its position among arguments does not matter
+    let is_stdin = args.file.iter().any(|file| file == STDIN);
+    args.file.retain(|file| file != STDIN);
+    if is_stdin && (args.from_null || args.files_from.as_deref() == Some(STDIN)) {
+        eprintln!("'-' cannot be hashed, as stdin is used to read list of files");
+        std::process::exit(1);
+    }
+
+    let mut seeds = HashMap::new();
+    if let Some(list) = args.files_from.as_deref() {
+        if let Err(error) = read_files_from(list, &args.kind, &mut args.file, &mut seeds) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    }
+
+    if args.from_null {
+        if let Err(error) = read_null_delimited(io::stdin().lock(), &mut args.file) {
+            eprintln!("{}: error reading: {}", STDIN, error);
+            std::process::exit(1);
+        }
+    }
+
+    if is_stdin {
+        args.file.push(STDIN.to_owned());
+    } else if args.file.is_empty() && args.string.is_empty() && args.files_from.is_none() && !args.from_null {
+        //Waiting for user to type input is unlikely to be intended
+        if io::stdin().is_terminal() {
+            eprintln!("No file specified. Specify files to hash or pipe data to stdin");
+            std::process::exit(1);
+        }
+        args.file.push(STDIN.to_owned());
+    }
+
+    if args.expect.as_deref() == Some(STDIN) && args.file.iter().any(|file| file == STDIN) {
+        eprintln!("'-' cannot be hashed, as stdin is used to read expected hash");
+        std::process::exit(1);
+    }
+
+    if let Err(error) = validate(&args) {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+
+    if let Some(head) = args.head {
+        args.length = Some(head);
+    }
+
+    //Colors are only for humans
+    if args.quiet {
+        args.color = Color::Never;
+    }
+
+    if args.tag {
+        args.format = Format::Bsd;
+    }
+
+    //Checksum file should be verifiable with --check
+    if args.output.is_some() && !args.verbose && !args.no_names && args.kind.len() == 1 && matches!(args.format, Format::Default) {
+        args.format = Format::Coreutils;
+    }
+
+    if let Some(base) = args.relative_to.as_deref() {
+        let normalized = match fs::metadata(base) {
+            Ok(meta) if meta.is_dir() => normalize_path(Path::new(base)),
+            Ok(_) => Err(io::ErrorKind::NotADirectory.into()),
+            Err(error) => Err(error),
+        };
+        match normalized {
+            Ok(normalized) => args.relative_to = Some(normalized.to_string_lossy().into_owned()),
+            Err(error) => {
+                eprintln!("--relative-to {}: {}", base, OpenReason(&error));
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let secret = match args.secret.as_deref().map(load_secret) {
+        Some(Ok(secret)) => Some(secret),
+        Some(Err(error)) => {
+            eprintln!("Invalid secret: {}", error);
+            std::process::exit(1);
+        },
+        None => None,
+    };
+
+    let mut state = None;
+    if args.save_state.is_some() || args.resume_state.is_some() {
+        match load_state(&args, seed) {
+            Ok(loaded) => match args.offset.checked_add(loaded.len()) {
+                Some(offset) => {
+                    args.offset = offset;
+                    state = Some(loaded);
+                },
+                None => {
+                    eprintln!("--offset overflows with {} bytes already hashed", loaded.len());
+                    std::process::exit(1);
+                }
+            },
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let params = FileParams {
+        global: Params {
+            seed,
+            secret,
+        },
+        seeds,
+    };
+
+    //Ctrl-C stops starting new work, while letting work in progress finish
+    interrupt::install();
+
+    if args.check {
+        let is_ok = check(&args, &params, &args.file);
+        if interrupt::is_interrupted() {
+            std::process::exit(interrupt::EXIT_CODE);
+        } else if !is_ok {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    //Inputs, that fail before hashing starts
+    let mut rejected = 0;
+    let files = match args.tree_hash {
+        //Directories are walked when their tree is hashed
+        true if args.glob => expand_globs(core::mem::take(&mut args.file), &mut rejected),
+        true => core::mem::take(&mut args.file),
+        false => collect_files(core::mem::take(&mut args.file), &args, &mut rejected),
+    };
+
+    if let Some(base) = args.relative_to.as_deref() {
+        for file in files.iter().filter(|file| *file != STDIN) {
+            if let Err(absolute) = relative_path(Path::new(base), file) {
+                match args.outside_base {
+                    OutsideBase::Error => {
+                        eprintln!("{}: is outside of --relative-to {}", file, base);
+                        std::process::exit(1);
+                    },
+                    OutsideBase::Absolute => if log::is_enabled(log::Level::Warn) {
+                        eprintln!("{}: WARNING: outside of --relative-to {}, printing as {}", file, base, absolute);
+                    },
+                }
+            }
+        }
+    }
+
+    if args.dry_run {
+        let terminator = line_terminator(&args);
+        let mut stdout = io::stdout().lock();
+        let result = files.iter().try_for_each(|file| write!(stdout, "{}{}", file, terminator)).and_then(|_| stdout.flush());
+        if let Err(error) = result {
+            eprintln!("{}: cannot write: {}", STDOUT, error);
+            std::process::exit(1);
+        } else if rejected > 0 {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(expected) = args.expect.as_deref() {
+        let expected = match expected {
+            STDIN => match read_expected() {
+                Ok(expected) => Cow::Owned(expected),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    std::process::exit(1);
+                }
+            },
+            expected => Cow::Borrowed(expected),
+        };
+        if !expect(&args, &params, &files, &expected) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args.compare {
+        if !compare(&args, &params, &files) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let (out, out_name): (Box<dyn Write>, &str) = match args.output.as_deref() {
+        Some(path) => match fs::OpenOptions::new().write(true).create(true).append(args.append).truncate(!args.append).open(path) {
+            Ok(file) => (Box::new(io::BufWriter::new(file)), path),
+            Err(error) => {
+                eprintln!("{}: cannot open: {}", path, OpenReason(&error));
+                std::process::exit(1);
+            }
+        },
+        None => (Box::new(io::stdout()), STDOUT),
+    };
+
+    let mut printer = Printer::new(&args, &params, out, out_name);
+    printer.begin();
+    printer.rejected(rejected);
+
+    for text in args.string.iter() {
+        let name = format!("\"{}\"", text);
+        printer.result(&name, hash_string(&args.kind, &params.global, text).map_err(HashError::Read));
+    }
+
+    if let Some(state) = state.as_mut() {
+        match files.as_slice() {
+            [file] if args.string.is_empty() && !args.combined => printer.result(file, hash_resumable(&args, file, state)),
+            _ => {
+                eprintln!("--save-state and --resume-state require exactly one input");
+                printer.is_ok = false;
+            }
+        }
+    } else if let Some(records) = args.records {
+        //Only stdin is ensured on start
+        let mut count = 0usize;
+        let result = open_file(STDIN, &args).map_err(HashError::Open).and_then(|mut input| {
+            hash_records(&args.kind, &params.global, &mut input, records.delimiter(), |digest| {
+                count += 1;
+                printer.digest(&count.to_string(), digest);
+                //Output is likely closed, while input may never end
+                !printer.is_write_failed
+            }).map_err(HashError::Read)
+        });
+        if let Err(error) = result {
+            printer.error(STDIN, &error);
+        }
+    } else if args.tree_hash {
+        for dir in files.iter() {
+            if interrupt::is_interrupted() {
+                break;
+            }
+            match hash_tree(&args, &params, dir) {
+                Ok(digest) => printer.digest(dir, digest),
+                Err((file, error)) => {
+                    printer.error(&file, &error);
+                    if args.fail_fast {
+                        break;
+                    }
+                },
+            }
+        }
+    } else if args.combined {
+        match hash_combined(&args, &params, &files) {
+            Ok(digest) => {
+                printer.digest(COMBINED, digest);
+                //Combined digest covers every file
+                printer.hashed_count = files.len();
+            },
+            Err((file, error)) => printer.error(file, &error),
+        }
+    } else if args.total {
+        //Accumulates the same data as --combined, while files are hashed
+        let mut totals = args.kind.iter().map(|kind| kind.hasher(&params.global)).collect::<Vec<_>>();
+        let mut update_totals = |chunk: &[u8]| for hasher in totals.iter_mut() {
+            hasher.update(chunk);
+        };
+        let mut total_size = 0u64;
+        let mut is_complete = true;
+        for file in files.iter() {
+            if interrupt::is_interrupted() {
+                is_complete = false;
+                break;
+            }
+            feed_name(&args, file, &mut update_totals);
+            let result = hash_file_with(&args, &args.kind, &params, file, &mut update_totals);
+            match &result {
+                Ok(digest) => total_size = total_size.saturating_add(digest.size),
+                Err(_) => is_complete = false,
+            }
+            printer.result(file, result);
+            if !is_complete && args.fail_fast {
+                break;
+            }
+        }
+
+        if is_complete {
+            printer.digest(TOTAL, Digest {
+                hashes: totals.iter().map(|hasher| hasher.finish()).collect(),
+                size: total_size,
+            });
+            //Total is not a file on its own
+            printer.hashed_count -= 1;
+            printer.hashed_size -= total_size;
+        } else {
+            eprintln!("{}: not computed, as not every file is hashed", TOTAL);
+        }
+    } else if args.sort != Sort::None {
+        let mut results = collect_results(&args, &params, &files);
+        sort_results(args.sort, &files, &mut results);
+        for (idx, result) in results {
+            printer.result(&files[idx], result);
+        }
+    } else if args.find_duplicates {
+        let mut results = collect_results(&args, &params, &files);
+        results.sort_by_key(|(idx, _)| *idx);
+        let mut groups = Vec::new();
+        //Files of different size are never duplicates, whatever their hashes are
+        let mut group_idx = HashMap::new();
+        for (idx, result) in results {
+            match result {
+                Ok(digest) => {
+                    let key = (digest.size, digest.hashes.iter().map(HashResult::as_u128).collect::<Vec<_>>());
+                    let group = *group_idx.entry(key).or_insert_with(|| {
+                        groups.push(Vec::new());
+                        groups.len() - 1
+                    });
+                    groups[group].push((idx, digest));
+                },
+                Err(error) => printer.error(&files[idx], &error),
+            }
+        }
+
+        let mut is_first = true;
+        for group in groups {
+            match group.len() {
+                1 => for (_, digest) in group {
+                    printer.hashed_count += 1;
+                    printer.hashed_size = printer.hashed_size.saturating_add(digest.size);
+                },
+                _ => {
+                    if !is_first {
+                        printer.group_separator();
+                    }
+                    is_first = false;
+                    for (idx, digest) in group {
+                        printer.digest(&files[idx], digest);
+                    }
+                },
+            }
+        }
+    } else if args.jobs > 1 && files.len() > 1 {
+        if printer.is_ndjson() {
+            //Streams results in order of completion
+            hash_files_parallel(&args, &params, &files, args.jobs, |idx, result| printer.result(&files[idx], result));
+        } else {
+            //Failed files are released in order too, so output is stable across runs
+            let mut ordered = Ordered::new();
+            hash_files_parallel(&args, &params, &files, args.jobs, |idx, result| {
+                ordered.push(idx, result, |idx, result| printer.result(&files[idx], result));
+            });
+            //Results wait for preceding ones only if hashing stopped early
+            ordered.drain(|idx, result| printer.result(&files[idx], result));
+        }
+    } else {
+        //Progress goes to stderr, but is only useful when user watches output.
+        let is_progress = args.force_progress || (args.progress && io::stdout().is_terminal());
+        for file in files.iter() {
+            if interrupt::is_interrupted() {
+                break;
+            }
+            let result = match is_progress {
+                true => hash_file_with_progress(&args, &params, file),
+                false => hash_file(&args, &params, file),
+            };
+            let is_failed = result.is_err();
+            printer.result(file, result);
+            if is_failed && args.fail_fast {
+                break;
+            }
+        }
+    }
+
+    if args.error_summary {
+        printer.error_summary(files.len() + args.string.len() + rejected);
+    }
+    printer.finish();
+
+    let is_interrupted = interrupt::is_interrupted();
+    //Partial summary tells how far hashing got before interruption
+    if (args.summary && !args.quiet) || is_interrupted {
+        let prefix = match is_interrupted {
+            true => "Interrupted: hashed",
+            false => "Hashed",
+        };
+        let files = match printer.hashed_count {
+            1 => "file",
+            _ => "files",
+        };
+        eprintln!("{} {} {}, {} in {:.1}s", prefix, printer.hashed_count, files, format_size(printer.hashed_size), start.elapsed().as_secs_f64());
+    }
+
+    if is_interrupted {
+        std::process::exit(interrupt::EXIT_CODE);
+    }
+
+    if !printer.is_ok {
+        std::process::exit(1);
     }
 }
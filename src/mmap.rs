@@ -0,0 +1,77 @@
+//!Read-only memory mapping of files.
+//!
+//!Available only on 64bit unix systems, elsewhere mapping always fails and caller is expected
+//!to fall back to streaming read.
+
+use std::io;
+use std::fs::File;
+
+#[cfg(all(unix, target_pointer_width = "64"))]
+mod sys {
+    use core::ffi::{c_int, c_void};
+
+    pub const PROT_READ: c_int = 1;
+    pub const MAP_PRIVATE: c_int = 2;
+    pub const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+
+    extern "C" {
+        pub fn mmap(addr: *mut c_void, len: usize, prot: c_int, flags: c_int, fd: c_int, offset: i64) -> *mut c_void;
+        pub fn munmap(addr: *mut c_void, len: usize) -> c_int;
+    }
+}
+
+///Read-only view of the file's content.
+pub struct Mmap {
+    ptr: *const u8,
+    len: usize,
+}
+
+impl Mmap {
+    #[cfg(all(unix, target_pointer_width = "64"))]
+    ///Maps `len` bytes of the `file` into memory.
+    ///
+    ///`len` must be non-zero.
+    pub fn map(file: &File, len: usize) -> io::Result<Self> {
+        use std::os::unix::io::AsRawFd;
+
+        debug_assert_ne!(len, 0);
+
+        let ptr = unsafe {
+            sys::mmap(core::ptr::null_mut(), len, sys::PROT_READ, sys::MAP_PRIVATE, file.as_raw_fd(), 0)
+        };
+
+        if ptr == sys::MAP_FAILED {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(Self {
+                ptr: ptr as *const u8,
+                len,
+            })
+        }
+    }
+
+    #[cfg(not(all(unix, target_pointer_width = "64")))]
+    ///Maps `len` bytes of the `file` into memory.
+    ///
+    ///Not supported on this platform.
+    pub fn map(_file: &File, _len: usize) -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "mmap is not supported"))
+    }
+
+    #[inline(always)]
+    ///Returns mapped content.
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe {
+            core::slice::from_raw_parts(self.ptr, self.len)
+        }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        #[cfg(all(unix, target_pointer_width = "64"))]
+        unsafe {
+            sys::munmap(self.ptr as *mut _, self.len);
+        }
+    }
+}
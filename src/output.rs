@@ -0,0 +1,236 @@
+//!Formatting of hashes and lines of output.
+
+use std::io::{self, Write};
+
+use crate::{HashKind, HashResult};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+///Layout of hash lines.
+pub enum Format {
+    ///`<file>:<hash>`
+    Default,
+    ///`<hash>  <file>`
+    Coreutils,
+    ///`<LABEL> (<file>) = <hash>`
+    Bsd,
+}
+
+impl core::str::FromStr for Format {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.eq_ignore_ascii_case("default") {
+            Ok(Self::Default)
+        } else if text.eq_ignore_ascii_case("coreutils") {
+            Ok(Self::Coreutils)
+        } else if text.eq_ignore_ascii_case("bsd") {
+            Ok(Self::Bsd)
+        } else {
+            Err(())
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+///Representation of hash value.
+pub enum Encoding {
+    ///Decimal integer.
+    Decimal,
+    ///Zero-padded hex of big-endian bytes.
+    Hex,
+    ///Base64 of big-endian bytes with padding.
+    Base64,
+    ///URL safe base64 of big-endian bytes without padding.
+    Base64Url,
+    ///UUID v4 made of 128bit hash, with shorter hashes in decimal.
+    Uuid,
+}
+
+#[derive(Clone, Copy, Debug)]
+///Formatting of hash value.
+pub struct HashFormat {
+    ///Representation of hash.
+    pub encoding: Encoding,
+    ///Whether hex digits are uppercase.
+    pub uppercase: bool,
+    ///Number of digits to pad hex with leading zeros to.
+    pub width: Option<usize>,
+}
+
+impl HashFormat {
+    ///Formats `hash` according to encoding.
+    ///
+    ///```rust
+    ///use xxhash_cli::HashResult;
+    ///use xxhash_cli::output::{Encoding, HashFormat};
+    ///
+    ///let format = |encoding| HashFormat {
+    ///    encoding,
+    ///    uppercase: false,
+    ///    width: None,
+    ///};
+    ///let hash = HashResult::U32(0x02cc5d05);
+    ///assert_eq!(format(Encoding::Decimal).format(hash), "46947589");
+    ///assert_eq!(format(Encoding::Hex).format(hash), "02cc5d05");
+    ///assert_eq!(format(Encoding::Base64).format(hash), "AsxdBQ==");
+    ///assert_eq!(format(Encoding::Uuid).format(hash), "46947589");
+    ///```
+    pub fn format(&self, hash: HashResult) -> String {
+        match (self.encoding, hash) {
+            (Encoding::Uuid, HashResult::U128(hash)) => {
+                let uuid = lolid::Uuid::from_bytes(hash.to_le_bytes()).set_variant().set_version(lolid::Version::Random);
+                uuid.to_string()
+            },
+            (Encoding::Base64, hash) => hash.to_base64(false),
+            (Encoding::Base64Url, hash) => hash.to_base64(true),
+            (Encoding::Hex, hash) => self.hex(hash),
+            (Encoding::Decimal | Encoding::Uuid, hash) => hash.as_u128().to_string(),
+        }
+    }
+
+    ///Formats `hash` as hex string, padded to width, regardless of encoding.
+    pub fn hex(&self, hash: HashResult) -> String {
+        let hex = hash.to_hex(self.uppercase);
+        match self.width {
+            Some(width) => format!("{:0>1$}", hex, width),
+            None => hex,
+        }
+    }
+}
+
+///Layout of lines of hashes.
+pub struct LineFormat {
+    ///Layout of line.
+    pub format: Format,
+    ///Separator between file and hash in default format.
+    pub separator: String,
+    ///Ending of each line.
+    pub terminator: &'static str,
+    ///Replacement of algorithm label in bsd format.
+    pub label: Option<String>,
+    ///Whether file names are omitted in default format.
+    pub no_names: bool,
+    ///Whether hashes of multiple algorithms are written, in which case default format labels each with algorithm name.
+    pub is_multiple: bool,
+}
+
+impl LineFormat {
+    ///Writes line with `hash` of `file` computed by `kind`.
+    pub fn write(&self, out: &mut dyn Write, file: &str, kind: &HashKind, hash: &str) -> io::Result<()> {
+        let separator = &self.separator;
+        let terminator = self.terminator;
+        match self.format {
+            Format::Default if self.no_names && self.is_multiple => write!(out, "{}{separator}{hash}{terminator}", kind.name()),
+            Format::Default if self.no_names => write!(out, "{hash}{terminator}"),
+            Format::Default if self.is_multiple => write!(out, "{file}{separator}{}{separator}{hash}{terminator}", kind.name()),
+            Format::Default => write!(out, "{file}{separator}{hash}{terminator}"),
+            Format::Coreutils => write!(out, "{hash}  {file}{terminator}"),
+            Format::Bsd => write!(out, "{} ({file}) = {hash}{terminator}", self.label.as_deref().unwrap_or(kind.label())),
+        }
+    }
+}
+
+///Writes JSON string literal.
+pub fn write_json_str(out: &mut String, text: &str) {
+    use core::fmt::Write;
+
+    out.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if ch.is_control() => {
+                let _ = write!(out, "\\u{:04x}", ch as u32);
+            },
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+}
+
+///Formats size in bytes using binary units.
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(format: Format, no_names: bool, is_multiple: bool) -> String {
+        line_with(format, ":", "dir/a", no_names, is_multiple)
+    }
+
+    fn line_with(format: Format, separator: &str, file: &str, no_names: bool, is_multiple: bool) -> String {
+        let format = LineFormat {
+            format,
+            separator: separator.to_owned(),
+            terminator: "\n",
+            label: None,
+            no_names,
+            is_multiple,
+        };
+        let mut out = Vec::new();
+        format.write(&mut out, file, &HashKind::Xxh64, "ef46db3751d8e999").expect("to write");
+        String::from_utf8(out).expect("UTF-8")
+    }
+
+    #[test]
+    fn should_write_lines_in_every_format() {
+        assert_eq!(line(Format::Default, false, false), "dir/a:ef46db3751d8e999\n");
+        assert_eq!(line(Format::Default, false, true), "dir/a:xxh64:ef46db3751d8e999\n");
+        assert_eq!(line(Format::Default, true, false), "ef46db3751d8e999\n");
+        assert_eq!(line(Format::Default, true, true), "xxh64:ef46db3751d8e999\n");
+        assert_eq!(line(Format::Coreutils, false, false), "ef46db3751d8e999  dir/a\n");
+        assert_eq!(line(Format::Bsd, false, false), "XXH64 (dir/a) = ef46db3751d8e999\n");
+    }
+
+    #[test]
+    fn should_separate_windows_path_by_custom_separator() {
+        let file = "C:\\data\\file.bin";
+        assert_eq!(line_with(Format::Default, "\t", file, false, false), "C:\\data\\file.bin\tef46db3751d8e999\n");
+        assert_eq!(line_with(Format::Default, " = ", file, false, true), "C:\\data\\file.bin = xxh64 = ef46db3751d8e999\n");
+        assert_eq!(line_with(Format::Coreutils, "\t", file, false, false), "ef46db3751d8e999  C:\\data\\file.bin\n");
+    }
+
+    #[test]
+    fn should_pad_hex_to_width() {
+        let format = HashFormat {
+            encoding: Encoding::Hex,
+            uppercase: true,
+            width: Some(32),
+        };
+        assert_eq!(format.format(HashResult::U32(0xab)), "000000000000000000000000000000AB");
+        assert_eq!(format.hex(HashResult::U64(0xab)), "000000000000000000000000000000AB");
+    }
+
+    #[test]
+    fn should_escape_json_strings() {
+        let mut out = String::new();
+        write_json_str(&mut out, "a\"b\\c\nd\te\u{1}");
+        assert_eq!(out, "\"a\\\"b\\\\c\\nd\\te\\u0001\"");
+    }
+
+    #[test]
+    fn should_format_sizes() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KiB");
+        assert_eq!(format_size(1536 * 1024), "1.5 MiB");
+    }
+}
@@ -0,0 +1,162 @@
+//!Reading of sparse files, skipping holes.
+//!
+//!Holes are found via `SEEK_DATA` and `SEEK_HOLE`, available on 64bit Linux, Android, FreeBSD and macOS.
+//!Elsewhere, or when file system does not support them, whole file is read as data.
+
+use std::io::{self, Read, Seek};
+use std::fs::File;
+
+#[cfg(all(target_pointer_width = "64", any(target_os = "linux", target_os = "android", target_os = "freebsd", target_os = "macos")))]
+mod sys {
+    use core::ffi::c_int;
+
+    #[cfg(not(target_os = "macos"))]
+    pub const SEEK_DATA: c_int = 3;
+    #[cfg(not(target_os = "macos"))]
+    pub const SEEK_HOLE: c_int = 4;
+    #[cfg(target_os = "macos")]
+    pub const SEEK_DATA: c_int = 4;
+    #[cfg(target_os = "macos")]
+    pub const SEEK_HOLE: c_int = 3;
+    ///Returned by `SEEK_DATA` when there is no data after offset.
+    pub const ENXIO: i32 = 6;
+
+    extern "C" {
+        pub fn lseek(fd: c_int, offset: i64, whence: c_int) -> i64;
+    }
+}
+
+///Kind of region to seek to.
+#[derive(Clone, Copy)]
+enum Region {
+    Data,
+    Hole,
+}
+
+#[cfg(all(target_pointer_width = "64", any(target_os = "linux", target_os = "android", target_os = "freebsd", target_os = "macos")))]
+///Returns start of first region of `kind` at or after `offset`, or `None` if there is no data after `offset`.
+fn seek_region(file: &File, offset: u64, kind: Region) -> io::Result<Option<u64>> {
+    use std::os::unix::io::AsRawFd;
+
+    let whence = match kind {
+        Region::Data => sys::SEEK_DATA,
+        Region::Hole => sys::SEEK_HOLE,
+    };
+    let offset = i64::try_from(offset).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    match unsafe { sys::lseek(file.as_raw_fd(), offset, whence) } {
+        result if result >= 0 => Ok(Some(result as u64)),
+        _ => match io::Error::last_os_error() {
+            error if error.raw_os_error() == Some(sys::ENXIO) => Ok(None),
+            error => Err(error),
+        },
+    }
+}
+
+#[cfg(not(all(target_pointer_width = "64", any(target_os = "linux", target_os = "android", target_os = "freebsd", target_os = "macos"))))]
+///Returns start of first region of `kind` at or after `offset`, or `None` if there is no data after `offset`.
+///
+///Not supported on this platform.
+fn seek_region(_file: &File, _offset: u64, _kind: Region) -> io::Result<Option<u64>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "SEEK_DATA is not supported"))
+}
+
+///Reader of regular file, that yields zeros for holes instead of reading them.
+///
+///Yields exactly the same bytes as plain read.
+pub struct SparseReader {
+    file: File,
+    buffer: Vec<u8>,
+    zeros: Vec<u8>,
+    ///Current position within file.
+    pos: u64,
+    ///Position to stop at.
+    end: u64,
+    ///End of current region.
+    region_end: u64,
+    is_hole: bool,
+    ///Whether holes cannot be detected, so rest of file is read as data.
+    is_unsupported: bool,
+}
+
+impl SparseReader {
+    ///Creates reader of `file` range from `start` to `end`, yielding chunks of up to `size` bytes.
+    ///
+    ///`size` must be non-zero.
+    pub fn new(file: File, size: usize, start: u64, end: u64) -> Self {
+        debug_assert_ne!(size, 0);
+
+        Self {
+            file,
+            buffer: vec![0u8; size],
+            zeros: vec![0u8; size],
+            pos: start,
+            end,
+            region_end: start,
+            is_hole: false,
+            is_unsupported: false,
+        }
+    }
+
+    ///Finds region starting at current position.
+    fn next_region(&mut self) -> io::Result<()> {
+        let data = match self.is_unsupported {
+            true => Ok(Some(self.pos)),
+            false => seek_region(&self.file, self.pos, Region::Data),
+        };
+
+        let (is_hole, region_end) = match data {
+            Ok(Some(data)) if data > self.pos => (true, data),
+            //Trailing hole
+            Ok(None) => (true, self.end),
+            Ok(Some(_)) => match self.is_unsupported {
+                true => (false, self.end),
+                //End of file counts as hole
+                false => (false, seek_region(&self.file, self.pos, Region::Hole)?.filter(|hole| *hole > self.pos).unwrap_or(self.end)),
+            },
+            Err(_) => {
+                self.is_unsupported = true;
+                (false, self.end)
+            },
+        };
+
+        self.is_hole = is_hole;
+        self.region_end = region_end.min(self.end);
+        if !is_hole {
+            self.file.seek(io::SeekFrom::Start(self.pos))?;
+        }
+        Ok(())
+    }
+
+    ///Gets next chunk, if any.
+    pub fn next(&mut self) -> io::Result<Option<&[u8]>> {
+        if self.pos >= self.end {
+            return Ok(None);
+        }
+        if self.pos >= self.region_end {
+            self.next_region()?;
+        }
+
+        let len = usize::try_from(self.region_end - self.pos).unwrap_or(usize::MAX).min(self.buffer.len());
+        if self.is_hole {
+            self.pos += len as u64;
+            return Ok(Some(&self.zeros[..len]));
+        }
+
+        let size = loop {
+            match self.file.read(&mut self.buffer[..len]) {
+                Ok(size) => break size,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        };
+
+        if size == 0 {
+            //File is truncated
+            self.end = self.pos;
+            Ok(None)
+        } else {
+            self.pos += size as u64;
+            Ok(Some(&self.buffer[..size]))
+        }
+    }
+}
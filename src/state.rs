@@ -0,0 +1,324 @@
+//!Hasher state that can be saved and resumed later.
+//!
+//!Streaming hashers of `xxhash-rust` do not expose their internal state, hence XXH32 and XXH64
+//!are computed here according to specification, producing identical hashes.
+//!XXH3 variants are not supported, as their state includes large internal buffer and secret.
+
+use crate::{HashKind, HashResult};
+
+const PRIME32_1: u32 = 0x9E3779B1;
+const PRIME32_2: u32 = 0x85EBCA77;
+const PRIME32_3: u32 = 0xC2B2AE3D;
+const PRIME32_4: u32 = 0x27D4EB2F;
+const PRIME32_5: u32 = 0x165667B1;
+
+const PRIME64_1: u64 = 0x9E3779B185EBCA87;
+const PRIME64_2: u64 = 0xC2B2AE3D27D4EB4F;
+const PRIME64_3: u64 = 0x165667B19E3779F9;
+const PRIME64_4: u64 = 0x85EBCA77C2B2AE63;
+const PRIME64_5: u64 = 0x27D4EB2F165667C5;
+
+///First line of serialized state, identifying format.
+const HEADER: &str = "xxhash-cli state 1";
+
+#[inline(always)]
+const fn round32(acc: u32, input: u32) -> u32 {
+    acc.wrapping_add(input.wrapping_mul(PRIME32_2)).rotate_left(13).wrapping_mul(PRIME32_1)
+}
+
+#[inline(always)]
+const fn round64(acc: u64, input: u64) -> u64 {
+    acc.wrapping_add(input.wrapping_mul(PRIME64_2)).rotate_left(31).wrapping_mul(PRIME64_1)
+}
+
+#[inline(always)]
+const fn merge_round64(acc: u64, val: u64) -> u64 {
+    (acc ^ round64(0, val)).wrapping_mul(PRIME64_1).wrapping_add(PRIME64_4)
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]])
+}
+
+fn finalize32(mut result: u32, data: &[u8]) -> u32 {
+    let mut words = data.chunks_exact(4);
+    for word in &mut words {
+        result = result.wrapping_add(read_u32(word).wrapping_mul(PRIME32_3)).rotate_left(17).wrapping_mul(PRIME32_4);
+    }
+    for byte in words.remainder() {
+        result = result.wrapping_add((*byte as u32).wrapping_mul(PRIME32_5)).rotate_left(11).wrapping_mul(PRIME32_1);
+    }
+
+    result ^= result >> 15;
+    result = result.wrapping_mul(PRIME32_2);
+    result ^= result >> 13;
+    result = result.wrapping_mul(PRIME32_3);
+    result ^ result >> 16
+}
+
+fn finalize64(mut result: u64, data: &[u8]) -> u64 {
+    let mut words = data.chunks_exact(8);
+    for word in &mut words {
+        result ^= round64(0, read_u64(word));
+        result = result.rotate_left(27).wrapping_mul(PRIME64_1).wrapping_add(PRIME64_4);
+    }
+    let mut data = words.remainder();
+    if data.len() >= 4 {
+        result ^= (read_u32(data) as u64).wrapping_mul(PRIME64_1);
+        result = result.rotate_left(23).wrapping_mul(PRIME64_2).wrapping_add(PRIME64_3);
+        data = &data[4..];
+    }
+    for byte in data {
+        result ^= (*byte as u64).wrapping_mul(PRIME64_5);
+        result = result.rotate_left(11).wrapping_mul(PRIME64_1);
+    }
+
+    result ^= result >> 33;
+    result = result.wrapping_mul(PRIME64_2);
+    result ^= result >> 29;
+    result = result.wrapping_mul(PRIME64_3);
+    result ^ result >> 32
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+
+    (0..text.len()).step_by(2).map(|idx| u8::from_str_radix(&text[idx..idx + 2], 16).ok()).collect()
+}
+
+///Resumable streaming state of XXH32 or XXH64.
+///
+///State is serialized as text via `Display` and restored via `FromStr`.
+///
+///```rust
+///use xxhash_cli::{HashKind, HashResult};
+///use xxhash_cli::state::State;
+///
+///let data = (0..200u8).collect::<Vec<_>>();
+///for len in 0..data.len() {
+///    let data = &data[..len];
+///    for split in [0, len / 3, len] {
+///        let mut state = State::new(HashKind::Xxh64, 1).expect("to support xxh64");
+///        state.update(&data[..split]);
+///        let mut state: State = state.to_string().parse().expect("to restore");
+///        assert_eq!(state.len(), split as u64);
+///        state.update(&data[split..]);
+///        assert_eq!(state.finish(), HashResult::U64(xxhash_rust::xxh64::xxh64(data, 1)));
+///
+///        let mut state = State::new(HashKind::Xxh32, 1).expect("to support xxh32");
+///        state.update(&data[..split]);
+///        let mut state: State = state.to_string().parse().expect("to restore");
+///        state.update(&data[split..]);
+///        assert_eq!(state.finish(), HashResult::U32(xxhash_rust::xxh32::xxh32(data, 1)));
+///    }
+///}
+///
+///assert!(State::new(HashKind::Xxh3, 0).is_none());
+///```
+#[derive(Clone, Debug)]
+pub struct State {
+    kind: HashKind,
+    seed: u64,
+    ///Total number of hashed bytes.
+    len: u64,
+    ///Accumulators, using only lower 32bit for XXH32.
+    acc: [u64; 4],
+    ///Data that does not fill whole stripe yet.
+    buffer: Vec<u8>,
+}
+
+impl State {
+    ///Returns whether state of `kind` can be saved.
+    pub const fn is_supported(kind: HashKind) -> bool {
+        matches!(kind, HashKind::Xxh32 | HashKind::Xxh64)
+    }
+
+    ///Creates initial state, if `kind` is supported.
+    ///
+    ///For `Xxh32` only lower 32bit of seed are used.
+    pub fn new(kind: HashKind, seed: u64) -> Option<Self> {
+        let acc = match kind {
+            HashKind::Xxh32 => {
+                let seed = seed as u32;
+                [
+                    seed.wrapping_add(PRIME32_1).wrapping_add(PRIME32_2) as u64,
+                    seed.wrapping_add(PRIME32_2) as u64,
+                    seed as u64,
+                    seed.wrapping_sub(PRIME32_1) as u64,
+                ]
+            },
+            HashKind::Xxh64 => [
+                seed.wrapping_add(PRIME64_1).wrapping_add(PRIME64_2),
+                seed.wrapping_add(PRIME64_2),
+                seed,
+                seed.wrapping_sub(PRIME64_1),
+            ],
+            _ => return None,
+        };
+
+        Some(Self {
+            kind,
+            seed,
+            len: 0,
+            acc,
+            buffer: Vec::new(),
+        })
+    }
+
+    #[inline(always)]
+    ///Returns algorithm of the state.
+    pub const fn kind(&self) -> HashKind {
+        self.kind
+    }
+
+    #[inline(always)]
+    ///Returns seed of the state.
+    pub const fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    #[inline(always)]
+    ///Returns number of bytes hashed so far.
+    pub const fn len(&self) -> u64 {
+        self.len
+    }
+
+    #[inline(always)]
+    ///Returns whether nothing is hashed yet.
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline(always)]
+    fn stripe_size(&self) -> usize {
+        match self.kind {
+            HashKind::Xxh32 => 16,
+            _ => 32,
+        }
+    }
+
+    fn round(&mut self, stripe: &[u8]) {
+        match self.kind {
+            HashKind::Xxh32 => for (idx, acc) in self.acc.iter_mut().enumerate() {
+                *acc = round32(*acc as u32, read_u32(&stripe[idx * 4..])) as u64;
+            },
+            _ => for (idx, acc) in self.acc.iter_mut().enumerate() {
+                *acc = round64(*acc, read_u64(&stripe[idx * 8..]));
+            },
+        }
+    }
+
+    ///Hashes provided chunk.
+    pub fn update(&mut self, mut input: &[u8]) {
+        self.len = self.len.wrapping_add(input.len() as u64);
+        let stripe_size = self.stripe_size();
+
+        if !self.buffer.is_empty() {
+            let fill_len = (stripe_size - self.buffer.len()).min(input.len());
+            self.buffer.extend_from_slice(&input[..fill_len]);
+            input = &input[fill_len..];
+            if self.buffer.len() < stripe_size {
+                return;
+            }
+
+            let buffer = core::mem::take(&mut self.buffer);
+            self.round(&buffer);
+            self.buffer = buffer;
+            self.buffer.clear();
+        }
+
+        let mut stripes = input.chunks_exact(stripe_size);
+        for stripe in &mut stripes {
+            self.round(stripe);
+        }
+        self.buffer.extend_from_slice(stripes.remainder());
+    }
+
+    ///Computes hash of all data hashed so far.
+    pub fn finish(&self) -> HashResult {
+        let acc = &self.acc;
+        let is_large = self.len >= self.stripe_size() as u64;
+        match self.kind {
+            HashKind::Xxh32 => {
+                let mut result = self.len as u32;
+                result = match is_large {
+                    true => result.wrapping_add((acc[0] as u32).rotate_left(1))
+                                  .wrapping_add((acc[1] as u32).rotate_left(7))
+                                  .wrapping_add((acc[2] as u32).rotate_left(12))
+                                  .wrapping_add((acc[3] as u32).rotate_left(18)),
+                    false => result.wrapping_add((self.seed as u32).wrapping_add(PRIME32_5)),
+                };
+                HashResult::U32(finalize32(result, &self.buffer))
+            },
+            _ => {
+                let result = match is_large {
+                    true => {
+                        let result = acc[0].rotate_left(1).wrapping_add(acc[1].rotate_left(7))
+                                                          .wrapping_add(acc[2].rotate_left(12))
+                                                          .wrapping_add(acc[3].rotate_left(18));
+                        acc.iter().fold(result, |result, acc| merge_round64(result, *acc))
+                    },
+                    false => self.seed.wrapping_add(PRIME64_5),
+                };
+                HashResult::U64(finalize64(result.wrapping_add(self.len), &self.buffer))
+            },
+        }
+    }
+}
+
+impl core::fmt::Display for State {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(fmt, "{}", HEADER)?;
+        writeln!(fmt, "algorithm {}", self.kind.name())?;
+        writeln!(fmt, "seed {}", self.seed)?;
+        writeln!(fmt, "length {}", self.len)?;
+        writeln!(fmt, "accumulators {:x} {:x} {:x} {:x}", self.acc[0], self.acc[1], self.acc[2], self.acc[3])?;
+        writeln!(fmt, "buffer {}", encode_hex(&self.buffer))
+    }
+}
+
+impl core::str::FromStr for State {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut lines = text.lines().map(|line| line.trim_end_matches('\r'));
+        if lines.next() != Some(HEADER) {
+            return Err("not a saved state".to_owned());
+        }
+
+        let mut field = |name: &str| match lines.next().map(|line| line.split_once(' ').unwrap_or((line, ""))) {
+            Some((key, value)) if key == name => Ok(value),
+            _ => Err(format!("missing {}", name)),
+        };
+
+        let algorithm = field("algorithm")?;
+        let kind = algorithm.parse::<HashKind>().map_err(|_| format!("unknown algorithm '{}'", algorithm))?;
+        let mut state = Self::new(kind, 0).ok_or_else(|| format!("{} state cannot be saved", kind.name()))?;
+        state.seed = field("seed")?.parse().map_err(|_| "invalid seed".to_owned())?;
+        state.len = field("length")?.parse().map_err(|_| "invalid length".to_owned())?;
+
+        let accumulators = field("accumulators")?.split(' ').map(|acc| u64::from_str_radix(acc, 16)).collect::<Result<Vec<_>, _>>();
+        state.acc = match accumulators.ok().and_then(|acc| <[u64; 4]>::try_from(acc).ok()) {
+            Some(acc) if kind != HashKind::Xxh32 || acc.iter().all(|acc| *acc <= u32::MAX as u64) => acc,
+            _ => return Err("invalid accumulators".to_owned()),
+        };
+
+        state.buffer = decode_hex(field("buffer")?).ok_or_else(|| "invalid buffer".to_owned())?;
+        //Buffer holds exactly the tail of data that does not fill whole stripe
+        if state.buffer.len() as u64 != state.len % state.stripe_size() as u64 {
+            return Err("buffer does not match length".to_owned());
+        }
+
+        Ok(state)
+    }
+}
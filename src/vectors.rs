@@ -0,0 +1,86 @@
+//!Known hashes of every algorithm, which both library tests and `--self-test` verify against.
+
+use crate::{HashKind, Params, Secret};
+
+///Seed of seeded vectors.
+pub const SEED: u64 = 2654435761;
+///Index of empty input.
+pub const EMPTY: usize = 0;
+///Index of input `abc`.
+pub const ABC: usize = 1;
+///Index of input longer than every algorithm's block.
+pub const LONG: usize = 2;
+///Names of inputs, in order of their indexes.
+pub const INPUTS: [&str; 3] = ["empty", "abc", "1000 bytes"];
+
+///Known hashes: algorithm, index of input, seed, whether custom secret is used and hash.
+pub const VECTORS: [(HashKind, usize, u64, bool, u128); 48] = [
+    (HashKind::Xxh32, EMPTY, 0, false, 0x02cc5d05),
+    (HashKind::Xxh32, EMPTY, SEED, false, 0x36b78ae7),
+    (HashKind::Xxh32, ABC, 0, false, 0x32d153ff),
+    (HashKind::Xxh32, ABC, SEED, false, 0xa1ae7709),
+    (HashKind::Xxh32, LONG, 0, false, 0x9aea58a9),
+    (HashKind::Xxh32, LONG, SEED, false, 0xfa4efd1c),
+    (HashKind::Xxh64, EMPTY, 0, false, 0xef46db3751d8e999),
+    (HashKind::Xxh64, EMPTY, SEED, false, 0xac75fda2929b17ef),
+    (HashKind::Xxh64, ABC, 0, false, 0x44bc2cf5ad770999),
+    (HashKind::Xxh64, ABC, SEED, false, 0x1318df30094a85fd),
+    (HashKind::Xxh64, LONG, 0, false, 0xd1bee8e4f0603bbf),
+    (HashKind::Xxh64, LONG, SEED, false, 0xf4f3f317b8871b2b),
+    (HashKind::Xxh3_64, EMPTY, 0, false, 0x2d06800538d394c2),
+    (HashKind::Xxh3_64, EMPTY, SEED, false, 0xf702ca3814de2125),
+    (HashKind::Xxh3_64, ABC, 0, false, 0x78af5f94892f3950),
+    (HashKind::Xxh3_64, ABC, SEED, false, 0x05823d71d740ed4f),
+    (HashKind::Xxh3_64, LONG, 0, false, 0x404e1417800eb486),
+    (HashKind::Xxh3_64, LONG, SEED, false, 0xc032e69a811dbf85),
+    (HashKind::Xxh3_64, EMPTY, 0, true, 0x86ea34d5129395d7),
+    (HashKind::Xxh3_64, EMPTY, SEED, true, 0xf702ca3814de2125),
+    (HashKind::Xxh3_64, ABC, 0, true, 0x05a97fdb69499d33),
+    (HashKind::Xxh3_64, ABC, SEED, true, 0x05823d71d740ed4f),
+    (HashKind::Xxh3_64, LONG, 0, true, 0x0b8ef493e4783151),
+    (HashKind::Xxh3_64, LONG, SEED, true, 0x0b8ef493e4783151),
+    (HashKind::Xxh3, EMPTY, 0, false, 0x99aa06d3014798d86001c324468d497f),
+    (HashKind::Xxh3, EMPTY, SEED, false, 0x92220ae55e14ab505444f7869c671ab0),
+    (HashKind::Xxh3, ABC, 0, false, 0x06b05ab6733a618578af5f94892f3950),
+    (HashKind::Xxh3, ABC, SEED, false, 0x86daf3b14b9b7f9305823d71d740ed4f),
+    (HashKind::Xxh3, LONG, 0, false, 0x17a0f7b2ab909d68404e1417800eb486),
+    (HashKind::Xxh3, LONG, SEED, false, 0x9a0f57e828e2639dc032e69a811dbf85),
+    (HashKind::Xxh3, EMPTY, 0, true, 0xf623fc960a4d0b42e2d4846561220d45),
+    (HashKind::Xxh3, EMPTY, SEED, true, 0x92220ae55e14ab505444f7869c671ab0),
+    (HashKind::Xxh3, ABC, 0, true, 0x52f1c675d4ae623c05a97fdb69499d33),
+    (HashKind::Xxh3, ABC, SEED, true, 0x86daf3b14b9b7f9305823d71d740ed4f),
+    (HashKind::Xxh3, LONG, 0, true, 0xcfb12a1bdea7687b0b8ef493e4783151),
+    (HashKind::Xxh3, LONG, SEED, true, 0xcfb12a1bdea7687b0b8ef493e4783151),
+    (HashKind::Xxh128, EMPTY, 0, false, 0x99aa06d3014798d86001c324468d497f),
+    (HashKind::Xxh128, EMPTY, SEED, false, 0x92220ae55e14ab505444f7869c671ab0),
+    (HashKind::Xxh128, ABC, 0, false, 0x06b05ab6733a618578af5f94892f3950),
+    (HashKind::Xxh128, ABC, SEED, false, 0x86daf3b14b9b7f9305823d71d740ed4f),
+    (HashKind::Xxh128, LONG, 0, false, 0x17a0f7b2ab909d68404e1417800eb486),
+    (HashKind::Xxh128, LONG, SEED, false, 0x9a0f57e828e2639dc032e69a811dbf85),
+    (HashKind::Xxh128, EMPTY, 0, true, 0xf623fc960a4d0b42e2d4846561220d45),
+    (HashKind::Xxh128, EMPTY, SEED, true, 0x92220ae55e14ab505444f7869c671ab0),
+    (HashKind::Xxh128, ABC, 0, true, 0x52f1c675d4ae623c05a97fdb69499d33),
+    (HashKind::Xxh128, ABC, SEED, true, 0x86daf3b14b9b7f9305823d71d740ed4f),
+    (HashKind::Xxh128, LONG, 0, true, 0xcfb12a1bdea7687b0b8ef493e4783151),
+    (HashKind::Xxh128, LONG, SEED, true, 0xcfb12a1bdea7687b0b8ef493e4783151),
+];
+
+///Returns input of `idx`.
+pub fn input(idx: usize) -> Vec<u8> {
+    match idx {
+        EMPTY => Vec::new(),
+        ABC => b"abc".to_vec(),
+        _ => (0..1000u32).map(|idx| (idx * 31 % 251) as u8).collect(),
+    }
+}
+
+///Returns parameters of vector, with custom secret when `is_secret` is set.
+pub fn params(seed: u64, is_secret: bool) -> Params {
+    Params {
+        seed,
+        secret: match is_secret {
+            true => Some(Secret(Box::new(core::array::from_fn(|idx| (idx * 7) as u8)))),
+            false => None,
+        },
+    }
+}
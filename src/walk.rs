@@ -0,0 +1,289 @@
+//!Recursive walk of directories.
+//!
+//!Entries are visited in sorted order, so that walk of the same tree always yields the same list of files.
+//!Skipped entries are reported as `skipped` events of `log`, with symbolic links also noted as warnings.
+
+use core::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+
+use crate::{OpenReason, glob, log};
+
+#[derive(Default)]
+///Options of directory walk.
+pub struct WalkOptions<'a> {
+    ///Wildcard patterns of files and directories to skip.
+    ///
+    ///Patterns with separator match path relative to walked directory, others match file name.
+    pub exclude: &'a [String],
+    ///How deep to descend, with 0 collecting only files directly within walked directory.
+    ///
+    ///Followed symbolic links count as directories.
+    pub max_depth: Option<usize>,
+    ///Whether to follow symbolic links, instead of skipping them.
+    pub follow_symlinks: bool,
+}
+
+#[derive(Debug)]
+///Failure to walk part of directory, which is then left out.
+pub enum WalkError {
+    ///Directory cannot be read.
+    ReadDir(PathBuf, io::Error),
+    ///Entry cannot be accessed.
+    Access(PathBuf, io::Error),
+    ///Path of file is not valid UTF-8.
+    NotUtf8(PathBuf),
+}
+
+impl fmt::Display for WalkError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ReadDir(path, error) => write!(fmt, "{}: cannot read directory: {}", path.display(), OpenReason(error)),
+            Self::Access(path, error) => write!(fmt, "{}: cannot access: {}", path.display(), OpenReason(error)),
+            Self::NotUtf8(path) => write!(fmt, "{}: path is not valid UTF-8", path.display()),
+        }
+    }
+}
+
+struct Walker<'a, F> {
+    options: &'a WalkOptions<'a>,
+    ///Directory walk started from, that exclude patterns with path are relative to.
+    root: &'a Path,
+    ///Canonical paths of directories being walked, to detect symlink cycles.
+    ancestors: Vec<PathBuf>,
+    on_error: F,
+}
+
+impl<F: FnMut(WalkError)> Walker<'_, F> {
+    ///Returns whether `path` matches any of exclude patterns.
+    fn is_excluded(&self, path: &Path) -> bool {
+        let name = path.file_name().and_then(|name| name.to_str());
+        let relative = path.strip_prefix(self.root).ok().and_then(Path::to_str);
+
+        self.options.exclude.iter().any(|pattern| match pattern.contains(['/', MAIN_SEPARATOR]) {
+            true => relative.map_or(false, |relative| glob::matches_path(pattern, relative)),
+            false => name.map_or(false, |name| glob::matches(pattern, name)),
+        })
+    }
+
+    fn walk(&mut self, dir: &Path, files: &mut Vec<String>) {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(error) => return (self.on_error)(WalkError::ReadDir(dir.to_owned(), error)),
+        };
+
+        match dir.canonicalize() {
+            Ok(canonical) => self.ancestors.push(canonical),
+            Err(error) => return (self.on_error)(WalkError::Access(dir.to_owned(), error)),
+        }
+
+        let mut paths = Vec::new();
+        for entry in entries {
+            match entry {
+                Ok(entry) => paths.push(entry.path()),
+                Err(error) => (self.on_error)(WalkError::ReadDir(dir.to_owned(), error)),
+            }
+        }
+        paths.sort_unstable();
+
+        for path in paths {
+            //Excluded directories are not walked at all
+            if self.is_excluded(&path) {
+                log::event(log::Level::Info, "skipped", &[("path", &path), ("reason", &"excluded")]);
+                continue;
+            }
+
+            let is_symlink = match path.symlink_metadata() {
+                Ok(meta) => meta.file_type().is_symlink(),
+                Err(error) => {
+                    (self.on_error)(WalkError::Access(path, error));
+                    continue;
+                }
+            };
+
+            if is_symlink && !self.options.follow_symlinks {
+                if log::is_enabled(log::Level::Warn) {
+                    eprintln!("{}: is a symbolic link, skipping", path.display());
+                }
+                log::event(log::Level::Info, "skipped", &[("path", &path), ("reason", &"symbolic link")]);
+                continue;
+            }
+
+            if path.is_dir() {
+                //Depth of subdirectory is the number of directories walked to reach it
+                if self.options.max_depth.is_some_and(|max_depth| self.ancestors.len() > max_depth) {
+                    log::event(log::Level::Info, "skipped", &[("path", &path), ("reason", &"max depth")]);
+                    continue;
+                }
+                if is_symlink {
+                    match path.canonicalize() {
+                        Ok(target) if self.ancestors.contains(&target) => {
+                            if log::is_enabled(log::Level::Warn) {
+                                eprintln!("{}: symbolic link loop, skipping", path.display());
+                            }
+                            log::event(log::Level::Info, "skipped", &[("path", &path), ("reason", &"symbolic link loop")]);
+                            continue;
+                        },
+                        Ok(_) => (),
+                        Err(error) => {
+                            (self.on_error)(WalkError::Access(path, error));
+                            continue;
+                        }
+                    }
+                }
+                self.walk(&path, files);
+            } else if path.is_file() {
+                match path.into_os_string().into_string() {
+                    Ok(path) => files.push(path),
+                    Err(path) => (self.on_error)(WalkError::NotUtf8(path.into())),
+                }
+            } else {
+                log::event(log::Level::Info, "skipped", &[("path", &path), ("reason", &"not regular file")]);
+            }
+        }
+
+        self.ancestors.pop();
+    }
+}
+
+///Walks `dir`, appending every regular file within to `files` in sorted order.
+///
+///Parts of directory that cannot be walked are passed to `on_error` and left out, while walk continues.
+pub fn walk_dir(dir: &Path, options: &WalkOptions<'_>, files: &mut Vec<String>, on_error: impl FnMut(WalkError)) {
+    let mut walker = Walker {
+        options,
+        root: dir,
+        ancestors: Vec::new(),
+        on_error,
+    };
+    walker.walk(dir, files);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///Temporary directory, removed once dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("xxhash-cli-walk-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).expect("to create directory");
+            Self(path)
+        }
+
+        fn file(&self, path: &str) {
+            let path = self.0.join(path);
+            fs::create_dir_all(path.parent().expect("parent")).expect("to create directory");
+            fs::write(path, b"data").expect("to write file");
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    ///Walks `dir`, returning paths of files relative to it.
+    fn walk(dir: &TempDir, options: &WalkOptions<'_>) -> Vec<String> {
+        let mut files = Vec::new();
+        walk_dir(&dir.0, options, &mut files, |error| panic!("{}", error));
+        files.iter().map(|file| Path::new(file).strip_prefix(&dir.0).expect("within dir").to_string_lossy().replace(MAIN_SEPARATOR, "/")).collect()
+    }
+
+    #[test]
+    fn should_walk_files_in_sorted_order() {
+        let dir = TempDir::new("sorted");
+        for file in ["b", "a", "sub/c", "sub/b", "a2/a"] {
+            dir.file(file);
+        }
+        fs::create_dir(dir.0.join("empty")).expect("to create directory");
+
+        assert_eq!(walk(&dir, &WalkOptions::default()), ["a", "a2/a", "b", "sub/b", "sub/c"]);
+    }
+
+    #[test]
+    fn should_exclude_by_name_or_relative_path() {
+        let dir = TempDir::new("exclude");
+        for file in ["a", "b.tmp", ".git/config", "sub/.git/config", "sub/skip/c", "sub/keep/c.tmp", "other/skip/d"] {
+            dir.file(file);
+        }
+
+        let names = [".git".to_owned(), "*.tmp".to_owned()];
+        let options = WalkOptions {
+            exclude: &names,
+            ..WalkOptions::default()
+        };
+        assert_eq!(walk(&dir, &options), ["a", "other/skip/d", "sub/skip/c"]);
+
+        let paths = ["sub/skip".to_owned(), "sub/*/*.tmp".to_owned()];
+        let options = WalkOptions {
+            exclude: &paths,
+            ..WalkOptions::default()
+        };
+        assert_eq!(walk(&dir, &options), [".git/config", "a", "b.tmp", "other/skip/d", "sub/.git/config"]);
+    }
+
+    #[test]
+    fn should_not_descend_beyond_max_depth() {
+        let dir = TempDir::new("depth");
+        for file in ["a", "d1/b", "d1/d2/c", "d1/d2/d3/e"] {
+            dir.file(file);
+        }
+
+        let depth = |max_depth| WalkOptions {
+            max_depth,
+            ..WalkOptions::default()
+        };
+        assert_eq!(walk(&dir, &depth(Some(0))), ["a"]);
+        assert_eq!(walk(&dir, &depth(Some(1))), ["a", "d1/b"]);
+        assert_eq!(walk(&dir, &depth(Some(2))), ["a", "d1/b", "d1/d2/c"]);
+        assert_eq!(walk(&dir, &depth(None)), ["a", "d1/b", "d1/d2/c", "d1/d2/d3/e"]);
+
+        //Followed symlink counts as directory at its own position
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("d1/d2", dir.0.join("link")).expect("to create symlink");
+            let follow = WalkOptions {
+                follow_symlinks: true,
+                ..depth(Some(1))
+            };
+            assert_eq!(walk(&dir, &follow), ["a", "d1/b", "link/c"]);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn should_not_follow_symlink_loops() {
+        use std::os::unix::fs::symlink;
+
+        let dir = TempDir::new("symlinks");
+        dir.file("a");
+        dir.file("sub/b");
+        symlink(".", dir.0.join("self")).expect("to create symlink");
+        symlink("..", dir.0.join("sub/parent")).expect("to create symlink");
+        symlink("a", dir.0.join("link")).expect("to create symlink");
+
+        assert_eq!(walk(&dir, &WalkOptions::default()), ["a", "sub/b"]);
+        let follow = WalkOptions {
+            follow_symlinks: true,
+            ..WalkOptions::default()
+        };
+        assert_eq!(walk(&dir, &follow), ["a", "link", "sub/b"]);
+    }
+
+    #[test]
+    fn should_report_unreadable_directory() {
+        let dir = TempDir::new("missing");
+        let mut files = Vec::new();
+        let mut errors = Vec::new();
+        walk_dir(&dir.0.join("missing"), &WalkOptions::default(), &mut files, |error| errors.push(error.to_string()));
+
+        assert!(files.is_empty());
+        assert_eq!(errors, [format!("{}: cannot read directory: no such file or directory", dir.0.join("missing").display())]);
+    }
+}
@@ -0,0 +1,573 @@
+//!Tests running `xxhash-cli` binary.
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use xxhash_rust::xxh64::xxh64;
+
+///Temporary directory, that runs happen within, removed once dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("xxhash-cli-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("to create directory");
+        Self(path)
+    }
+
+    ///Writes file at `path` relative to directory, creating its parents.
+    fn file(&self, path: &str, content: &[u8]) {
+        let path = self.0.join(path);
+        fs::create_dir_all(path.parent().expect("parent")).expect("to create directory");
+        fs::write(path, content).expect("to write file");
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+///Outcome of run.
+struct Run {
+    code: Option<i32>,
+    stdout: String,
+    stderr: String,
+}
+
+///Runs binary within `dir` with `args`, feeding `stdin`.
+///
+///Environment, that changes defaults, is cleared, so that only configuration within `dir` is used.
+fn run_with(dir: &TempDir, args: &[&str], stdin: &[u8]) -> Run {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_xxhash-cli")).args(args)
+                                                                 .current_dir(&dir.0)
+                                                                 .env("XDG_CONFIG_HOME", dir.0.join(".config"))
+                                                                 .env_remove("XXHASH_SEED")
+                                                                 .env_remove("XXHASH_DEFAULT")
+                                                                 .env_remove("XXHASH_OUTPUT")
+                                                                 .env_remove("NO_COLOR")
+                                                                 .stdin(Stdio::piped())
+                                                                 .stdout(Stdio::piped())
+                                                                 .stderr(Stdio::piped())
+                                                                 .spawn()
+                                                                 .expect("to run");
+    //Binary may exit without reading stdin
+    let _ = child.stdin.take().expect("stdin").write_all(stdin);
+    let output = child.wait_with_output().expect("to finish");
+    Run {
+        code: output.status.code(),
+        stdout: String::from_utf8(output.stdout).expect("UTF-8 stdout"),
+        stderr: String::from_utf8(output.stderr).expect("UTF-8 stderr"),
+    }
+}
+
+fn run(dir: &TempDir, args: &[&str]) -> Run {
+    run_with(dir, args, b"")
+}
+
+#[test]
+fn should_hash_existing_files_and_report_missing_ones() {
+    let dir = TempDir::new("missing");
+    dir.file("a", b"a");
+    dir.file("b", b"b");
+
+    let run = run(&dir, &["xxh64", "a", "missing", "b"]);
+    assert_eq!(run.code, Some(1));
+    assert_eq!(run.stdout, format!("a:{}\nb:{}\n", xxh64(b"a", 0), xxh64(b"b", 0)));
+    assert_eq!(run.stderr, "missing: cannot open: no such file or directory\n");
+}
+
+#[test]
+fn should_check_own_output_of_every_format() {
+    let dir = TempDir::new("check-formats");
+    dir.file("a", b"a");
+    dir.file("dir/b c", b"b");
+
+    for format in [&["--format", "default"][..], &["--format", "coreutils"], &["--tag"]] {
+        for encoding in [&[][..], &["--hex"], &["--hex", "--uppercase"], &["--canonical"]] {
+            let args = [&["xxh64"], format, encoding, &["a", "dir/b c"]].concat();
+            let sums = run(&dir, &args);
+            assert_eq!(sums.code, Some(0), "{:?}", args);
+            dir.file("sums", sums.stdout.as_bytes());
+
+            let check = run(&dir, &["-c", "xxh64", "sums"]);
+            assert_eq!(check.stdout, "a: OK\ndir/b c: OK\n", "{:?}", args);
+            assert_eq!(check.stderr, "2 OK, 0 FAILED, 0 missing\n", "{:?}", args);
+            assert_eq!(check.code, Some(0), "{:?}", args);
+        }
+    }
+}
+
+#[test]
+fn should_check_mixed_formats_using_algorithm_of_bsd_label() {
+    let dir = TempDir::new("check-mixed");
+    dir.file("a", b"a");
+    let sums = [
+        format!("XXH64 (a) = {:016x}", xxh64(b"a", 0)),
+        format!("XXH32 (a) = {:08X}", xxhash_rust::xxh32::xxh32(b"a", 0)),
+        format!("{}  a", xxhash_rust::xxh3::xxh3_128(b"a")),
+        format!("a:{:032x}", xxhash_rust::xxh3::xxh3_128(b"a")),
+        format!("XXH128 (a) = {:032x}", xxhash_rust::xxh3::xxh3_128(b"a")),
+        format!("XXH3 (a) = {:016x}", xxhash_rust::xxh3::xxh3_64(b"a")),
+        format!("XXH3 (a) = {:016x}", xxh64(b"a", 0)),
+    ];
+    dir.file("sums", sums.join("\n").as_bytes());
+
+    let run = run(&dir, &["-c", "sums"]);
+    assert_eq!(run.stdout, "a: OK\n".repeat(6) + "a: FAILED\n");
+    assert_eq!(run.stderr, "6 OK, 1 FAILED, 0 missing\n");
+    assert_eq!(run.code, Some(1));
+}
+
+#[test]
+fn should_report_line_of_improper_checksum() {
+    let dir = TempDir::new("check-lines");
+    dir.file("a", b"a");
+    dir.file("sums", format!("{:x}  a\n\njunk\nMD5 (a) = 0cc175b9c0f1b6a831c399e269772661\na:{}\n", xxh64(b"a", 0), xxh64(b"a", 0)).as_bytes());
+
+    let run = run(&dir, &["-c", "xxh64", "sums"]);
+    assert_eq!(run.stdout, "a: OK\na: OK\n");
+    assert_eq!(run.stderr, "sums:3: improperly formatted checksum line\nsums:4: unknown algorithm label 'MD5'\n2 OK, 0 FAILED, 0 missing\n");
+    assert_eq!(run.code, Some(0));
+}
+
+#[test]
+fn should_check_only_lines_of_requested_input_format() {
+    let dir = TempDir::new("check-input-format");
+    dir.file("a", b"a");
+    dir.file("sums", format!("{:x}  a\na:{:x}\n", xxh64(b"a", 0), xxh64(b"a", 0)).as_bytes());
+
+    let run = run(&dir, &["-c", "--input-format", "colon", "xxh64", "sums"]);
+    assert_eq!(run.stdout, "a: OK\n");
+    assert_eq!(run.stderr, "sums:1: improperly formatted checksum line\n1 OK, 0 FAILED, 0 missing\n");
+}
+
+#[test]
+fn should_print_only_failures_to_stderr_when_quiet() {
+    let dir = TempDir::new("quiet");
+    dir.file("a", b"a");
+    dir.file("b", b"b");
+    dir.file("sums", format!("{:x}  a\n{:x}  b\n{:x}  missing\n", xxh64(b"a", 0), xxh64(b"a", 0), xxh64(b"a", 0)).as_bytes());
+
+    let check = run(&dir, &["-c", "--quiet", "xxh64", "sums"]);
+    assert_eq!(check.stdout, "");
+    assert_eq!(check.stderr, "b: FAILED\nmissing: cannot open: no such file or directory\nmissing: FAILED open or read\n");
+    assert_eq!(check.code, Some(1));
+
+    let hash = run(&dir, &["--quiet", "xxh64", "a", "missing"]);
+    assert_eq!(hash.stdout, "");
+    assert_eq!(hash.stderr, "missing: cannot open: no such file or directory\n");
+    assert_eq!(hash.code, Some(1));
+
+    let json = run(&dir, &["--quiet", "--json", "xxh64", "a", "missing"]);
+    assert_eq!(json.stdout, "[\n{\"file\":\"missing\",\"error\":\"cannot open: no such file or directory\"}\n]\n");
+    assert_eq!(json.stderr, "");
+
+    let expect = run(&dir, &["--quiet", "--expect", "0", "xxh64", "a"]);
+    assert_eq!(expect.stdout, "");
+    assert_eq!(expect.stderr, "a: MISMATCH\n");
+    assert_eq!(expect.code, Some(1));
+}
+
+#[test]
+fn should_fail_on_glob_matching_no_files() {
+    let dir = TempDir::new("glob");
+    dir.file("a.txt", b"a");
+
+    let run1 = run(&dir, &["--glob", "--error-summary", "xxh64", "*.txt", "*.md"]);
+    assert_eq!(run1.stdout, format!("a.txt:{}\n", xxh64(b"a", 0)));
+    assert_eq!(run1.stderr, "*.md: no files match pattern\n{\"summary\":{\"opened\":1,\"hashed\":1,\"failed\":1,\"skipped\":0}}\n");
+    assert_eq!(run1.code, Some(1));
+
+    let dry_run = run(&dir, &["--glob", "--dry-run", "xxh64", "*.md"]);
+    assert_eq!(dry_run.stderr, "*.md: no files match pattern\n");
+    assert_eq!(dry_run.code, Some(1));
+
+    assert_eq!(run(&dir, &["--glob", "xxh64", "*.txt"]).code, Some(0));
+}
+
+#[test]
+fn should_report_unknown_algorithm_instead_of_missing_file() {
+    let dir = TempDir::new("unknown-kind");
+    dir.file("a", b"a");
+    dir.file("md4", b"md4");
+    let error = "unknown algorithm '{}', valid options: xxh32, xxh64, xxh3_64, xxh3, xxh128\n";
+
+    for kind in ["md5", "sha-256", "xxh63", "xxh64,crc32"] {
+        let run = run(&dir, &[kind, "a"]);
+        assert_eq!(run.stdout, "", "{}", kind);
+        assert_eq!(run.stderr, error.replace("{}", kind.rsplit(',').next().unwrap_or(kind)), "{}", kind);
+        assert_eq!(run.code, Some(1), "{}", kind);
+    }
+
+    //Existing files and paths are inputs hashed by default algorithm
+    let run1 = run(&dir, &["md4", "a"]);
+    assert_eq!(run1.stdout, format!("md4:{}\na:{}\n", xxhash_rust::xxh3::xxh3_128(b"md4"), xxhash_rust::xxh3::xxh3_128(b"a")));
+    let run2 = run(&dir, &["dir/md5", "a"]);
+    assert_eq!(run2.stderr, "dir/md5: cannot open: no such file or directory\n");
+    assert_eq!(run2.code, Some(1));
+}
+
+#[test]
+fn should_use_config_only_as_defaults_of_command_line() {
+    let dir = TempDir::new("config");
+    dir.file("a", b"a");
+    dir.file(".config/xxhash-cli/config.toml", b"algorithm = xxh64\nformat = \"coreutils\"\nseparator = \"=\"\n");
+    let hash = xxh64(b"a", 0);
+
+    assert_eq!(run(&dir, &["a"]).stdout, format!("{}  a\n", hash));
+    assert_eq!(run(&dir, &["xxh32", "a"]).stdout, format!("{}  a\n", xxhash_rust::xxh32::xxh32(b"a", 0)));
+    assert_eq!(run(&dir, &["--format", "default", "a"]).stdout, format!("a={}\n", hash));
+    assert_eq!(run(&dir, &["--separator", ":", "--format", "default", "a"]).stdout, format!("a:{}\n", hash));
+
+    //Configured format conflicting with options is ignored
+    let tag = run(&dir, &["--tag", "a"]);
+    assert_eq!((tag.code, tag.stdout, tag.stderr), (Some(0), format!("XXH64 (a) = {}\n", hash), String::new()));
+    let no_names = run(&dir, &["--no-names", "a"]);
+    assert_eq!((no_names.code, no_names.stdout, no_names.stderr), (Some(0), format!("{}\n", hash), String::new()));
+
+    //Conflicts among options themselves are still reported
+    let conflict = run(&dir, &["--format", "coreutils", "--tag", "a"]);
+    assert_eq!(conflict.stderr, "--tag cannot be combined with --format coreutils\n");
+    assert_eq!(conflict.code, Some(1));
+
+    dir.file(".config/xxhash-cli/config.toml", b"format = gnu\n");
+    let invalid = run(&dir, &["a"]);
+    assert_eq!(invalid.stderr, format!("{}:1: unknown format 'gnu'\n", dir.0.join(".config/xxhash-cli/config.toml").display()));
+    assert_eq!(invalid.code, Some(1));
+}
+
+#[test]
+fn should_verify_manifest_with_verify_ci_preset() {
+    let dir = TempDir::new("preset");
+    dir.file("a", b"a");
+    dir.file("b", b"b");
+    let (a, b) = (xxh64(b"a", 0), xxh64(b"b", 0));
+
+    let manifest = run(&dir, &["--preset", "manifest", "xxh64", "b", "a"]);
+    assert_eq!(manifest.stdout, format!("{:016x}  a\n{:016x}  b\n", a, b));
+    dir.file("sums", manifest.stdout.as_bytes());
+
+    let verify = run(&dir, &["--preset=verify-ci", "xxh64", "sums"]);
+    assert_eq!((verify.code, verify.stdout, verify.stderr), (Some(0), String::new(), String::new()));
+
+    dir.file("bad", format!("0000000000000000  a\n{:016x}  b\n", b).as_bytes());
+    let verify = run(&dir, &["--preset=verify-ci", "xxh64", "bad"]);
+    assert_eq!((verify.code, verify.stdout, verify.stderr), (Some(1), String::new(), "a: FAILED\n".to_owned()));
+
+    //Options of preset are overridden by explicit ones, even if they would conflict
+    assert_eq!(run(&dir, &["--preset", "manifest", "--tag", "xxh64", "b", "a"]).stdout, format!("XXH64 (a) = {:016x}\nXXH64 (b) = {:016x}\n", a, b));
+    assert_eq!(run(&dir, &["--preset", "manifest", "--no-names", "xxh64", "b", "a"]).stdout, format!("{:016x}\n{:016x}\n", a, b));
+    assert_eq!(run(&dir, &["--preset", "manifest", "--decimal", "--sort", "none", "xxh64", "b", "a"]).stdout, format!("{}  b\n{}  a\n", b, a));
+}
+
+#[test]
+fn should_hash_mapped_files_same_as_streamed() {
+    let dir = TempDir::new("mmap");
+    //Larger than files read at once
+    let big = (0..200_000u32).map(|idx| (idx * 31 % 251) as u8).collect::<Vec<_>>();
+    dir.file("empty", b"");
+    dir.file("small", b"abc");
+    dir.file("big", &big);
+
+    for kind in ["xxh32", "xxh64", "xxh3_64", "xxh3"] {
+        let streamed = run(&dir, &[kind, "empty", "small", "big"]);
+        let mapped = run(&dir, &["--mmap", kind, "empty", "small", "big"]);
+        assert_eq!(mapped.code, Some(0), "{}", kind);
+        assert_eq!(mapped.stdout, streamed.stdout, "{}", kind);
+    }
+
+    let mapped = run(&dir, &["--mmap", "xxh64", "empty", "big"]);
+    assert_eq!(mapped.stdout, format!("empty:{}\nbig:{}\n", xxh64(b"", 0), xxh64(&big, 0)));
+
+    //Stdin cannot be mapped, so it is streamed
+    let stdin = run_with(&dir, &["--mmap", "xxh64"], b"abc");
+    assert_eq!((stdin.code, stdin.stdout), (Some(0), format!("-:{}\n", xxh64(b"abc", 0))));
+}
+
+#[test]
+fn should_round_trip_nul_delimited_names() {
+    let dir = TempDir::new("nul");
+    dir.file("a b", b"a");
+    dir.file("new\nline", b"n");
+    let (a, n) = (xxh64(b"a", 0), xxh64(b"n", 0));
+
+    let list = run(&dir, &["--dry-run", "-z", "xxh64", "a b", "new\nline"]);
+    assert_eq!(list.stdout, "a b\0new\nline\0");
+
+    let hashes = run_with(&dir, &["-0", "-z", "xxh64"], list.stdout.as_bytes());
+    assert_eq!(hashes.code, Some(0));
+    assert_eq!(hashes.stdout, format!("a b:{}\0new\nline:{}\0", a, n));
+
+    let coreutils = run_with(&dir, &["-0", "-z", "--format", "coreutils", "xxh64"], list.stdout.as_bytes());
+    assert_eq!(coreutils.stdout, format!("{}  a b\0{}  new\nline\0", a, n));
+    let bsd = run_with(&dir, &["-0", "-z", "--tag", "--hex", "xxh64"], list.stdout.as_bytes());
+    assert_eq!(bsd.stdout, format!("XXH64 (a b) = {:016x}\0XXH64 (new\nline) = {:016x}\0", a, n));
+}
+
+#[test]
+fn should_check_malformed_lines_and_missing_files_by_strict_and_ignore_missing() {
+    let dir = TempDir::new("strict");
+    dir.file("a", b"a");
+    dir.file("sums", format!("{hash}  a\njunk\n{hash}  missing\n", hash = xxh64(b"a", 0)).as_bytes());
+    dir.file("junk", b"junk\n");
+
+    //--strict decides on malformed lines, while --ignore-missing decides on missing files
+    let cases = [
+        (&[][..], Some(1), true, Some(0)),
+        (&["--strict"], Some(1), true, Some(1)),
+        (&["--ignore-missing"], Some(0), false, Some(0)),
+        (&["--strict", "--ignore-missing"], Some(1), false, Some(1)),
+    ];
+    for (flags, code, is_missing_reported, junk_code) in cases {
+        let check = run(&dir, &[&["-c", "xxh64"], flags, &["sums"]].concat());
+        let (stdout, stderr) = match is_missing_reported {
+            true => ("a: OK\nmissing: FAILED open or read\n", "sums:2: improperly formatted checksum line\nmissing: cannot open: no such file or directory\n1 OK, 0 FAILED, 1 missing\n"),
+            false => ("a: OK\n", "sums:2: improperly formatted checksum line\n1 OK, 0 FAILED, 0 missing\n"),
+        };
+        assert_eq!((check.stdout.as_str(), check.stderr.as_str()), (stdout, stderr), "{:?}", flags);
+        assert_eq!(check.code, code, "{:?}", flags);
+
+        let check = run(&dir, &[&["-c", "xxh64"], flags, &["junk"]].concat());
+        assert_eq!((check.stdout.as_str(), check.stderr.as_str()), ("", "junk:1: improperly formatted checksum line\n0 OK, 0 FAILED, 0 missing\n"), "{:?}", flags);
+        assert_eq!(check.code, junk_code, "{:?}", flags);
+    }
+}
+
+#[test]
+fn should_hash_empty_stdin_and_nothing_of_empty_list() {
+    let dir = TempDir::new("empty");
+    dir.file("list", b"");
+
+    //Without files, stdin that is not terminal is hashed
+    let stdin = run(&dir, &["xxh64"]);
+    assert_eq!((stdin.code, stdin.stdout, stdin.stderr), (Some(0), format!("-:{}\n", xxh64(b"", 0)), String::new()));
+
+    //Explicitly empty list is not replaced by stdin
+    for args in [&["--files-from", "list", "xxh64"][..], &["-0", "xxh64"]] {
+        let empty = run(&dir, args);
+        assert_eq!((empty.code, empty.stdout.as_str(), empty.stderr.as_str()), (Some(0), "", ""), "{:?}", args);
+    }
+    let json = run(&dir, &["--json", "--files-from", "list", "xxh64"]);
+    assert_eq!((json.code, json.stdout.as_str(), json.stderr.as_str()), (Some(0), "[\n]\n", ""));
+}
+
+#[cfg(unix)]
+#[test]
+fn should_report_unreadable_file_and_continue() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new("unreadable");
+    dir.file("a", b"a");
+    dir.file("locked", b"locked");
+    let locked = dir.0.join("locked");
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).expect("to set permissions");
+    //Superuser reads regardless of permissions
+    if fs::File::open(&locked).is_ok() {
+        return;
+    }
+
+    let run = run(&dir, &["xxh64", "locked", "a"]);
+    assert_eq!(run.stdout, format!("a:{}\n", xxh64(b"a", 0)));
+    assert_eq!(run.stderr, "locked: cannot open: permission denied\n");
+    assert_eq!(run.code, Some(1));
+}
+
+#[test]
+fn should_hash_arguments_then_files_from_then_stdin() {
+    let dir = TempDir::new("order");
+    for name in ["a", "b", "c", "d", "e"] {
+        dir.file(name, name.as_bytes());
+    }
+    dir.file("list", b"d\nc\n");
+    let line = |name: &str| format!("{}:{}\n", name, xxh64(name.as_bytes(), 0));
+
+    let run1 = run_with(&dir, &["xxh64", "b", "-", "a", "--files-from", "list"], b"stdin");
+    assert_eq!(run1.code, Some(0));
+    assert_eq!(run1.stdout, [line("b"), line("a"), line("d"), line("c"), format!("-:{}\n", xxh64(b"stdin", 0))].concat());
+
+    let run2 = run_with(&dir, &["-0", "xxh64", "b", "--files-from", "list"], b"e\0a\0");
+    assert_eq!(run2.stdout, [line("b"), line("d"), line("c"), line("e"), line("a")].concat());
+}
+
+#[test]
+fn should_print_only_failures_and_summary_with_only_failed() {
+    let dir = TempDir::new("only-failed");
+    dir.file("a", b"a");
+    dir.file("b", b"b");
+    dir.file("sums", format!("{:x}  a\n{:x}  b\n{:x}  missing\n", xxh64(b"a", 0), xxh64(b"a", 0), xxh64(b"a", 0)).as_bytes());
+    dir.file("good", format!("{:x}  a\n{:x}  b\n", xxh64(b"a", 0), xxh64(b"b", 0)).as_bytes());
+
+    let check = run(&dir, &["-c", "--only-failed", "xxh64", "sums"]);
+    assert_eq!(check.stdout, "b: FAILED\nmissing: FAILED open or read\n");
+    assert_eq!(check.stderr, "missing: cannot open: no such file or directory\n1 OK, 1 FAILED, 1 missing\n");
+    assert_eq!(check.code, Some(1));
+
+    let check = run(&dir, &["-c", "--only-failed", "xxh64", "good"]);
+    assert_eq!((check.code, check.stdout.as_str(), check.stderr.as_str()), (Some(0), "", "2 OK, 0 FAILED, 0 missing\n"));
+}
+
+#[test]
+fn should_print_paths_relative_to_base() {
+    let dir = TempDir::new("relative");
+    dir.file("base/sub/a", b"a");
+    dir.file("out/o", b"o");
+    let root = fs::canonicalize(&dir.0).expect("to canonicalize");
+    let (a, o) = (xxh64(b"a", 0), xxh64(b"o", 0));
+
+    let inside = run(&dir, &["--relative-to", "base", "xxh64", "base/sub/a", "./base/../base/sub/a"]);
+    assert_eq!((inside.code, inside.stdout, inside.stderr), (Some(0), format!("sub/a:{}\nsub/a:{}\n", a, a), String::new()));
+    let absolute = run(&dir, &["--relative-to", "base", "xxh64", &root.join("base/sub/a").to_string_lossy()]);
+    assert_eq!(absolute.stdout, format!("sub/a:{}\n", a));
+
+    //Outside of base nothing is hashed by default
+    let outside = run(&dir, &["--relative-to", "base", "xxh64", "base/sub/a", "out/o"]);
+    assert_eq!(outside.stdout, "");
+    assert_eq!(outside.stderr, format!("out/o: is outside of --relative-to {}\n", root.join("base").display()));
+    assert_eq!(outside.code, Some(1));
+
+    let outside = run(&dir, &["--relative-to", "base", "--outside-base", "absolute", "xxh64", "base/sub/a", "out/o"]);
+    assert_eq!(outside.stdout, format!("sub/a:{}\n{}:{}\n", a, root.join("out/o").display(), o));
+    assert_eq!(outside.stderr, format!("out/o: WARNING: outside of --relative-to {}, printing as {}\n", root.join("base").display(), root.join("out/o").display()));
+    assert_eq!(outside.code, Some(0));
+}
+
+#[test]
+fn should_round_trip_batch_records() {
+    let dir = TempDir::new("batch");
+    let files: [(&str, &[u8]); 3] = [("a", b"a"), ("tab\tand\nnewline", b"content"), ("empty", b"")];
+    for (name, content) in files {
+        dir.file(name, content);
+    }
+
+    let batch = run(&dir, &[&["--batch", "xxh64"][..], &files.map(|(name, _)| name)].concat());
+    assert_eq!((batch.code, batch.stderr.as_str()), (Some(0), ""));
+    let records = batch.stdout.strip_suffix('\0').expect("NUL terminated").split('\0').collect::<Vec<_>>();
+    assert_eq!(records.len(), files.len());
+
+    for (record, (name, content)) in records.iter().zip(files) {
+        let fields = record.splitn(4, '\t').collect::<Vec<_>>();
+        let mtime = fs::metadata(dir.0.join(name)).and_then(|meta| meta.modified()).expect("mtime").duration_since(std::time::UNIX_EPOCH).expect("after epoch");
+        assert_eq!(fields, [content.len().to_string(), mtime.as_secs().to_string(), xxh64(content, 0).to_string(), name.to_owned()], "{:?}", name);
+    }
+
+    //Paths of records are usable as input as they are
+    let names = records.iter().map(|record| record.splitn(4, '\t').nth(3).expect("path").to_owned() + "\0").collect::<String>();
+    let again = run_with(&dir, &["--batch", "-0", "xxh64"], names.as_bytes());
+    assert_eq!(again.stdout, batch.stdout);
+}
+
+#[test]
+fn should_group_identical_files_with_find_duplicates() {
+    let dir = TempDir::new("duplicates");
+    for (name, content) in [("a", &b"same"[..]), ("unique", b"other"), ("b", b"same"), ("empty1", b""), ("empty2", b"")] {
+        dir.file(name, content);
+    }
+    let (same, empty) = (xxh64(b"same", 0), xxh64(b"", 0));
+
+    let run1 = run(&dir, &["--find-duplicates", "xxh64", "a", "unique", "b", "empty1", "empty2"]);
+    assert_eq!(run1.stdout, format!("a:{same}\nb:{same}\n\nempty1:{empty}\nempty2:{empty}\n"));
+    assert_eq!((run1.code, run1.stderr.as_str()), (Some(0), ""));
+
+    let run2 = run(&dir, &["--find-duplicates", "xxh64", "a", "unique"]);
+    assert_eq!((run2.code, run2.stdout.as_str()), (Some(0), ""));
+}
+
+#[test]
+fn should_fail_on_directory_regardless_of_log_level() {
+    let dir = TempDir::new("directory");
+    dir.file("a", b"a");
+    dir.file("d/b", b"b");
+
+    for level in ["error", "warn"] {
+        let run = run(&dir, &["--log-level", level, "--error-summary", "xxh64", "a", "d"]);
+        assert_eq!(run.stdout, format!("a:{}\n", xxh64(b"a", 0)), "{}", level);
+        assert_eq!(run.stderr, "d: is a directory\n{\"summary\":{\"opened\":1,\"hashed\":1,\"failed\":1,\"skipped\":0}}\n", "{}", level);
+        assert_eq!(run.code, Some(1), "{}", level);
+    }
+
+    let info = run(&dir, &["--log-level", "info", "xxh64", "d"]);
+    assert_eq!(info.stderr, "d: is a directory\nlevel=info event=skipped path=\"d\" reason=\"directory\"\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn should_note_skipped_symbolic_links() {
+    let dir = TempDir::new("symlink-note");
+    dir.file("d/a", b"a");
+    std::os::unix::fs::symlink("a", dir.0.join("d/link")).expect("to create symlink");
+    std::os::unix::fs::symlink(".", dir.0.join("d/self")).expect("to create symlink");
+
+    let warn = run(&dir, &["-r", "xxh64", "d"]);
+    assert_eq!(warn.stdout, format!("d/a:{}\n", xxh64(b"a", 0)));
+    assert_eq!(warn.stderr, "d/link: is a symbolic link, skipping\nd/self: is a symbolic link, skipping\n");
+    assert_eq!(warn.code, Some(0));
+
+    let follow = run(&dir, &["-r", "--follow-symlinks", "xxh64", "d"]);
+    assert_eq!(follow.stderr, "d/self: symbolic link loop, skipping\n");
+
+    let error = run(&dir, &["-r", "--log-level", "error", "xxh64", "d"]);
+    assert_eq!(error.stderr, "");
+}
+
+#[test]
+fn should_fail_when_ignore_missing_verifies_nothing() {
+    let dir = TempDir::new("verified-nothing");
+    dir.file("a", b"a");
+    dir.file("missing.sums", format!("{hash}  missing1\n{hash}  missing2\n", hash = xxh64(b"a", 0)).as_bytes());
+    dir.file("a.sums", format!("{}  a\n", xxh64(b"a", 0)).as_bytes());
+
+    let check = run(&dir, &["-c", "--ignore-missing", "xxh64", "missing.sums"]);
+    assert_eq!(check.stdout, "");
+    assert_eq!(check.stderr, "missing.sums: no file was verified\n0 OK, 0 FAILED, 0 missing\n");
+    assert_eq!(check.code, Some(1));
+
+    //Every checksum file has to verify something
+    let check = run(&dir, &["-c", "--ignore-missing", "xxh64", "a.sums", "missing.sums"]);
+    assert_eq!(check.stdout, "a: OK\n");
+    assert_eq!(check.stderr, "missing.sums: no file was verified\n1 OK, 0 FAILED, 0 missing\n");
+    assert_eq!(check.code, Some(1));
+}
+
+#[test]
+fn should_accept_only_secret_of_default_size() {
+    let dir = TempDir::new("secret");
+    let data = (0..1000u32).map(|idx| (idx * 31 % 251) as u8).collect::<Vec<_>>();
+    dir.file("data", &data);
+    for len in [136, 192, 200] {
+        dir.file(&format!("secret{}", len), &(0..len).map(|idx| (idx * 7) as u8).collect::<Vec<_>>());
+    }
+
+    let secret = (0..192).map(|idx| (idx * 7) as u8).collect::<Vec<_>>();
+    let run1 = run(&dir, &["--secret", "secret192", "xxh3_64", "data"]);
+    assert_eq!((run1.code, run1.stdout), (Some(0), format!("data:{}\n", xxhash_rust::xxh3::xxh3_64_with_secret(&data, &secret))));
+
+    for len in [136, 200] {
+        let run = run(&dir, &["--secret", &format!("secret{}", len), "xxh3_64", "data"]);
+        assert_eq!(run.stdout, "", "{}", len);
+        assert_eq!(run.stderr, format!("Invalid secret: secret must be exactly 192 bytes long, but got {} bytes\n", len));
+        assert_eq!(run.code, Some(1), "{}", len);
+    }
+}
+
+#[test]
+fn should_print_version_of_implementation_without_algorithm() {
+    let dir = TempDir::new("version");
+    let run = run(&dir, &["--version"]);
+    assert_eq!(run.code, Some(0));
+    assert_eq!(run.stdout, format!("xxhash-cli {}\nxxhash-rust 0.8.19 (features: xxh3, xxh64, xxh32)\nalgorithms: xxh32, xxh64, xxh3_64, xxh3, xxh128\n", env!("CARGO_PKG_VERSION")));
+}
+
+#[test]
+fn should_pass_self_test_of_every_known_vector() {
+    let dir = TempDir::new("self_test");
+    let run = run(&dir, &["--self-test"]);
+    assert_eq!(run.code, Some(0));
+    assert_eq!(run.stdout.lines().count(), xxhash_cli::vectors::VECTORS.len());
+    assert!(run.stdout.contains("xxh3 abc seed 0 with secret: PASS\n"), "{}", run.stdout);
+    assert!(run.stderr.contains(&format!("{} PASS, 0 FAIL", xxhash_cli::vectors::VECTORS.len())), "{}", run.stderr);
+}